@@ -0,0 +1,80 @@
+use crate::board::{Board, RuleVariant};
+use crate::bot::{SearchControl, SearchLimits, SearchOptions, MINIMAX_DEPTH};
+use crate::engine::{self, EngineKind};
+use crate::mcts::MctsOptions;
+use crate::tablebase;
+
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// `checkers analyse [--depth d | --time seconds] [--engine alphabeta|mcts|random] [--threads n] [--lines k] <fen>` prints
+// every report as the search makes it, with the best k moves when asked for, then the move it settles on
+pub fn run(args: &[String]) -> Result<(), String> {
+    let usage = "usage: checkers analyse [--depth d | --time seconds] [--engine alphabeta|mcts|random] [--threads n] [--lines k] <fen>";
+    let mut limits = SearchLimits::depth(MINIMAX_DEPTH);
+    let mut kind = EngineKind::ALPHABETA;
+    let mut options = SearchOptions { tablebase: tablebase::load_default().map(Arc::new), ..SearchOptions::default() };
+    let mut mcts = MctsOptions::default();
+    let mut fen = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => {
+                let value = args.next().ok_or("--depth expects a number")?;
+                limits = SearchLimits::depth(value.parse().map_err(|_| format!("invalid depth '{}'", value))?);
+            },
+            "--time" => {
+                let value = args.next().ok_or("--time expects a number of seconds")?;
+                let seconds: f32 = value.parse().ok().filter(|seconds: &f32| *seconds > 0.0).ok_or(format!("invalid time '{}'", value))?;
+                limits = SearchLimits::time(Duration::from_secs_f32(seconds));
+            },
+            "--engine" => {
+                let value = args.next().ok_or("--engine expects alphabeta, mcts or random")?;
+                kind = EngineKind::parse(value).ok_or(format!("unknown engine '{}'", value))?;
+            },
+            "--threads" => {
+                let value = args.next().ok_or("--threads expects a number")?;
+                options.threads = value.parse().ok().filter(|&n| n > 0).ok_or(format!("invalid number of threads '{}'", value))?;
+            },
+            "--lines" => {
+                let value = args.next().ok_or("--lines expects a number")?;
+                options.multi_pv = value.parse().ok().filter(|&n| n > 0).ok_or(format!("invalid number of lines '{}'", value))?;
+                mcts.multi_pv = options.multi_pv;
+            },
+            _ => fen = Some(arg)
+        }
+    }
+
+    let board = Board::from_fen(fen.ok_or(usage)?, RuleVariant::CASUAL)?;
+    if board.is_game_over() {
+        return Err("the game is already over in this position".to_string());
+    }
+
+    let mut engine = engine::create(kind, options, mcts);
+    engine.set_position(&board);
+    let (sender, reports) = mpsc::channel();
+    let control = SearchControl::with_info(limits, sender);
+
+    let result = thread::scope(|scope| {
+        let search = scope.spawn(|| engine.search_with(&control));
+        while !search.is_finished() {
+            if let Ok(info) = reports.recv_timeout(Duration::from_millis(50)) {
+                println!("{}", info);
+            }
+        }
+        return search.join().unwrap();
+    });
+    for info in reports.try_iter() {
+        println!("{}", info);
+    }
+
+    match (result.best_move, result.ponder_move) {
+        (None, _) => println!("the game is over"),
+        (Some(m), Some(reply)) => println!("best move {} expecting {}", m.to_notation(), reply.to_notation()),
+        (Some(m), None) => println!("best move {}", m.to_notation())
+    }
+    return Ok(());
+}
@@ -0,0 +1,164 @@
+use crate::ballot::{self, BallotDeck};
+use crate::board::{Player, RuleVariant};
+use crate::bot::{SearchLimits, SearchOptions};
+use crate::engine::{AlphaBeta, Engine, MonteCarlo, RandomMover};
+use crate::eval::EvalParams;
+use crate::game::Game;
+use crate::mcts::{MctsOptions, Playout};
+use crate::pdn::Outcome;
+use crate::rng::Rng;
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+pub const ARENA_DEPTH: i32 = 6;
+pub const ARENA_GAMES: usize = 10;
+
+// Bots that just shuffle kings around would never finish, so a game this long is called a draw
+pub const MAX_GAME_PLIES: usize = 300;
+
+// "default" and weights files are played by the alpha-beta search
+fn load(name: &str, mcts: &MctsOptions) -> Result<Box<dyn Engine>, String> {
+    let eval = match name {
+        "mcts" => return Ok(Box::new(MonteCarlo::new(mcts.clone()))),
+        "random" => return Ok(Box::new(RandomMover::new())),
+        "default" => EvalParams::default(),
+        _ => EvalParams::load(Path::new(name))?
+    };
+    return Ok(Box::new(AlphaBeta::new(SearchOptions { eval: eval, ..SearchOptions::default() })));
+}
+
+// Plays one game between two engines, returning the finished game and its winner, or None for a draw
+pub fn play_game<'a>(mut game: Game, black: &'a mut dyn Engine, red: &'a mut dyn Engine, depth: i32) -> (Game, Option<Player>) {
+    black.new_game();
+    red.new_game();
+    while game.history.len() < MAX_GAME_PLIES {
+        if let Some(winner) = game.winner() {
+            return (game, Some(winner));
+        }
+
+        let engine = if game.board.get_turn() == Player::BLACK { &mut *black } else { &mut *red };
+        engine.set_position(&game.board);
+        let Some(m) = engine.search(SearchLimits::depth(depth)).best_move else {
+            let winner = game.board.get_winner();
+            return (game, Some(winner));
+        };
+        game.play(m);
+    }
+
+    return (game, None);
+}
+
+// Plays a few random moves so the games do not all repeat each other
+fn random_opening(plies: usize, rng: &mut Rng) -> Game {
+    let mut game = Game::new(RuleVariant::CASUAL);
+    while game.history.len() < plies && game.winner().is_none() {
+        let moves = game.board.get_all_legal_moves(game.board.get_turn());
+        game.play(moves[rng.below(moves.len())]);
+    }
+    return game;
+}
+
+// Every position of a finished game with its result, in the format `checkers tune` reads
+fn dump(file: &mut File, game: &Game, winner: Option<Player>) -> Result<(), String> {
+    let outcome = winner.map_or(Outcome::DRAW, Outcome::WIN);
+    for board in game.positions() {
+        writeln!(file, "{} {}", board.to_fen(), outcome.to_pdn()).map_err(|e| format!("could not write positions: {}", e))?;
+    }
+    return Ok(());
+}
+
+// `checkers arena [--games n] [--depth d] [--random-plies n] [--ballots two|three] [--dump positions.txt] <first> <second>`
+// plays two weights files (or "default", "mcts" for the Monte Carlo engine or "random") against each other, swapping
+// colours every game. With ballots each opening is played twice in a row, once with either colour. The Monte
+// Carlo engine takes --iterations, --exploration and --playout random|captures.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut games = ARENA_GAMES;
+    let mut depth = ARENA_DEPTH;
+    let mut random_plies = 0;
+    let mut deck = BallotDeck::OFF;
+    let mut dump_file: Option<File> = None;
+    let mut names: Vec<&str> = Vec::new();
+    let mut mcts = MctsOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => {
+                let value = args.next().ok_or("--games expects a number")?;
+                games = value.parse().map_err(|_| format!("invalid number of games '{}'", value))?;
+            },
+            "--depth" => {
+                let value = args.next().ok_or("--depth expects a number")?;
+                depth = value.parse().map_err(|_| format!("invalid depth '{}'", value))?;
+            },
+            "--random-plies" => {
+                let value = args.next().ok_or("--random-plies expects a number")?;
+                random_plies = value.parse().map_err(|_| format!("invalid number of plies '{}'", value))?;
+            },
+            "--ballots" => {
+                let value = args.next().ok_or("--ballots expects two or three")?;
+                deck = BallotDeck::parse(value).ok_or(format!("unknown ballot deck '{}'", value))?;
+            },
+            "--iterations" => {
+                let value = args.next().ok_or("--iterations expects a number")?;
+                mcts.iterations = value.parse().map_err(|_| format!("invalid number of iterations '{}'", value))?;
+            },
+            "--exploration" => {
+                let value = args.next().ok_or("--exploration expects a number")?;
+                mcts.exploration = value.parse().map_err(|_| format!("invalid exploration constant '{}'", value))?;
+            },
+            "--playout" => {
+                let value = args.next().ok_or("--playout expects random or captures")?;
+                mcts.playout = Playout::parse(value).ok_or(format!("unknown playout policy '{}'", value))?;
+            },
+            "--dump" => {
+                let path = args.next().ok_or("--dump expects a file")?;
+                let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| format!("could not open {}: {}", path, e))?;
+                dump_file = Some(file);
+            },
+            _ => names.push(arg)
+        }
+    }
+
+    if names.len() != 2 {
+        return Err("usage: checkers arena [--games n] [--depth d] [--random-plies n] [--ballots two|three] [--dump positions.txt] [--iterations n] [--exploration c] [--playout random|captures] <first.toml|default|mcts|random> <second.toml|default|mcts|random>".to_string());
+    }
+    let mut players = [load(names[0], &mcts)?, load(names[1], &mcts)?];
+    println!("{} ({}) against {} ({})", names[0], players[0].name(), names[1], players[1].name());
+    let mut rng = Rng::from_time();
+    let ballots = deck.ballots();
+
+    // Wins for the first and second player, then draws
+    let mut score = [0, 0, 0];
+    for i in 0..games {
+        // The first player has black, and so the first move, in the even games
+        let first_black = i % 2 == 0;
+        let [first, second] = &mut players;
+        let (black, red) = if first_black { (first, second) } else { (second, first) };
+        let opening = if ballots.is_empty() {
+            random_opening(random_plies, &mut rng)
+        } else {
+            // The ballots are played with forced captures, as the decks are defined
+            ballot::start(ballots[i / 2 % ballots.len()], RuleVariant::AMERICAN)?
+        };
+        let (game, winner) = play_game(opening, black.as_mut(), red.as_mut(), depth);
+        if let Some(file) = &mut dump_file {
+            dump(file, &game, winner)?;
+        }
+
+        let outcome = match winner {
+            None => 2,
+            Some(Player::BLACK) => if first_black { 0 } else { 1 },
+            Some(Player::RED) => if first_black { 1 } else { 0 }
+        };
+        score[outcome] += 1;
+        println!("Game {:>3}: {} as black, {} in {} plies", i + 1, names[if first_black { 0 } else { 1 }],
+            match outcome { 0 => format!("{} won", names[0]), 1 => format!("{} won", names[1]), _ => "drawn".to_string() },
+            game.history.len());
+    }
+
+    println!("{}: {} wins, {}: {} wins, {} draws", names[0], score[0], names[1], score[1], score[2]);
+    return Ok(());
+}
@@ -0,0 +1,74 @@
+use crate::board::Board;
+use crate::bot::{SearchControl, SearchInfo, SearchLimits, SearchResult, MAX_SEARCH_DEPTH};
+use crate::engine::Engine;
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+// A search on its own thread, so the window keeps drawing while the bot thinks
+pub struct BackgroundSearch {
+    pub board: Board,
+    // A pondering search runs without limits until the move it predicted is played
+    pub pondering: bool,
+    control: Arc<SearchControl>,
+    info: Receiver<SearchInfo>,
+    handle: Option<JoinHandle<SearchResult>>
+}
+
+impl BackgroundSearch {
+    // A search that was stopped still holds the engine until it has wound down, the next one waits for it.
+    // An engine whose last search panicked is used all the same, every search starts by setting the position.
+    pub fn start(board: Board, limits: SearchLimits, engine: Arc<Mutex<Box<dyn Engine>>>) -> Self {
+        let (sender, info) = mpsc::channel();
+        let control = Arc::new(SearchControl::with_info(limits, sender));
+        let handle = {
+            let (board, control) = (board.clone(), control.clone());
+            thread::spawn(move || {
+                let mut engine = engine.lock().unwrap_or_else(PoisonError::into_inner);
+                engine.set_position(&board);
+                return engine.search_with(&control);
+            })
+        };
+        Self { board: board, pondering: false, control: control, info: info, handle: Some(handle) }
+    }
+
+    // Searches the position after the predicted reply for as long as the human thinks
+    pub fn ponder(board: Board, engine: Arc<Mutex<Box<dyn Engine>>>) -> Self {
+        let mut search = BackgroundSearch::start(board, SearchLimits::depth(MAX_SEARCH_DEPTH), engine);
+        search.pondering = true;
+        return search;
+    }
+
+    // The predicted move was played, so the search keeps everything it has found and now works to the real limits
+    pub fn hit(&mut self, limits: SearchLimits) {
+        self.pondering = false;
+        let deep_enough = limits.time.is_none() && self.control.completed().is_some_and(|result| result.depth >= limits.depth);
+        if deep_enough {
+            self.control.stop();
+        } else {
+            self.control.set_limits(limits);
+        }
+    }
+
+    // Every report since the last call, oldest first
+    pub fn reports(&self) -> Vec<SearchInfo> {
+        return self.info.try_iter().collect();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        return self.handle.as_ref().is_none_or(|handle| handle.is_finished());
+    }
+
+    // None if the search thread panicked
+    pub fn result(mut self) -> Option<SearchResult> {
+        return self.handle.take().and_then(|handle| handle.join().ok());
+    }
+}
+
+// A search that is no longer wanted is told to stop and left to wind down on its own
+impl Drop for BackgroundSearch {
+    fn drop(&mut self) {
+        self.control.stop();
+    }
+}
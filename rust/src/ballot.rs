@@ -0,0 +1,76 @@
+use crate::board::RuleVariant;
+use crate::game::Game;
+use crate::pdn::parse_move;
+use crate::rng::Rng;
+
+use serde::{Deserialize, Serialize};
+
+const TWO_MOVE_BALLOTS: &str = include_str!("../data/two_move_ballots.txt");
+const THREE_MOVE_BALLOTS: &str = include_str!("../data/three_move_ballots.txt");
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BallotDeck {
+    OFF,
+    TWO,
+    THREE
+}
+
+impl BallotDeck {
+    pub fn parse(s: &str) -> Option<BallotDeck> {
+        return match s {
+            "off" => Some(BallotDeck::OFF),
+            "two" => Some(BallotDeck::TWO),
+            "three" => Some(BallotDeck::THREE),
+            _ => None
+        };
+    }
+
+    // Each ballot is a line of moves such as "9-13 21-17 5-9"
+    pub fn ballots(&self) -> Vec<&'static str> {
+        let text = match self {
+            BallotDeck::OFF => "",
+            BallotDeck::TWO => TWO_MOVE_BALLOTS,
+            BallotDeck::THREE => THREE_MOVE_BALLOTS
+        };
+        return text.lines().map(|line| line.trim()).filter(|line| !line.is_empty() && !line.starts_with('#')).collect();
+    }
+
+    pub fn random(&self, rng: &mut Rng) -> Option<&'static str> {
+        let ballots = self.ballots();
+        if ballots.is_empty() {
+            return None;
+        }
+        return Some(ballots[rng.below(ballots.len())]);
+    }
+}
+
+// Plays the moves of a ballot, a multi-jump counts as one move
+pub fn play_ballot(game: &mut Game, ballot: &str) -> Result<(), String> {
+    for token in ballot.split_whitespace() {
+        for m in parse_move(&game.board, token).map_err(|e| format!("ballot {}: {}", ballot, e))? {
+            game.play(m);
+        }
+    }
+    return Ok(());
+}
+
+pub fn start(ballot: &str, rules: RuleVariant) -> Result<Game, String> {
+    let mut game = Game::new(rules);
+    play_ballot(&mut game, ballot)?;
+    return Ok(game);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_ballot_plays_with_forced_captures() {
+        for deck in [BallotDeck::TWO, BallotDeck::THREE] {
+            for ballot in deck.ballots() {
+                assert!(start(ballot, RuleVariant::AMERICAN).is_ok(), "{}", ballot);
+            }
+        }
+    }
+}
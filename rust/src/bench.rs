@@ -0,0 +1,94 @@
+use crate::board::{Board, RuleVariant};
+use crate::bot::{SearchLimits, SearchOptions};
+use crate::engine::{AlphaBeta, Engine};
+
+use std::time::Instant;
+
+// A fixed set of positions so node counts are comparable between versions of the search
+pub const BENCH_POSITIONS: [&str; 8] = [
+    "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12",
+    "B:W13,19,20,21,25,26,27,28,29,30,31,32:B1,2,3,5,6,7,8,9,10,11,12,18",
+    "B:W18,21,23,25,26,27,28,29,30,31,32:B1,2,3,4,6,7,9,10,12,14,15",
+    "B:W17,19,22,25,26,27,29,30,31,32:B1,2,3,4,5,7,8,12,13,15",
+    "B:W14,20,24,25,26,27,29,30,31,32:B1,2,3,4,5,6,7,12,16,17",
+    "W:W15,18,19,21,22,23,26,27,29,30,31,32:B1,2,3,5,6,8,9,10,12,14,16,20",
+    "W:WK14,K22,27:BK5,10",
+    "B:W18,22,K30:B6,K11,15"
+];
+
+pub const BENCH_DEPTH: i32 = 8;
+
+fn run_set(depth: i32, options: SearchOptions) -> u64 {
+    let mut engine = AlphaBeta::new(options);
+    let mut total = 0;
+
+    for fen in BENCH_POSITIONS {
+        let board = Board::from_fen(fen, RuleVariant::CASUAL).unwrap();
+        let start = Instant::now();
+        engine.set_position(&board);
+        let result = engine.search(SearchLimits::depth(depth));
+
+        println!("{:<70} {:>6} {:>12} nodes {:>10} quiescence {:>8.0} ms", fen, result.best_move.map_or("-".to_string(), |m| m.to_notation()), result.nodes, result.quiescence_nodes, start.elapsed().as_secs_f64() * 1000.0);
+        total += result.nodes + result.quiescence_nodes;
+    }
+
+    return total;
+}
+
+// Searches every position to the same depth with more and more threads. Lazy SMP gains nothing in the
+// node count, its threads search overlapping trees, so what matters is the time it takes to reach the depth.
+fn run_scaling(depth: i32, max_threads: usize) {
+    let mut counts: Vec<usize> = (0..).map(|power| 1 << power).take_while(|&threads| threads < max_threads).collect();
+    counts.push(max_threads);
+
+    println!("{:>7} {:>12} {:>10} {:>12} {:>8}", "threads", "nodes", "ms", "nodes/s", "speedup");
+    let mut baseline = 0.0;
+    for threads in counts {
+        let mut engine = AlphaBeta::new(SearchOptions { threads: threads, ..SearchOptions::default() });
+        let mut nodes = 0;
+        let start = Instant::now();
+        for fen in BENCH_POSITIONS {
+            let board = Board::from_fen(fen, RuleVariant::CASUAL).unwrap();
+            engine.set_position(&board);
+            let result = engine.search(SearchLimits::depth(depth));
+            nodes += result.nodes + result.quiescence_nodes;
+        }
+
+        let seconds = start.elapsed().as_secs_f64();
+        if threads == 1 {
+            baseline = seconds;
+        }
+        println!("{:>7} {:>12} {:>10.0} {:>12.0} {:>7.2}x", threads, nodes, seconds * 1000.0, nodes as f64 / seconds, baseline / seconds);
+    }
+}
+
+// `checkers bench [depth]` searches every benchmark position with and without move ordering,
+// `checkers bench [depth] --threads n` measures how the search scales from 1 to n threads
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut depth = BENCH_DEPTH;
+    let mut threads = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threads" => {
+                let value = args.next().ok_or("--threads expects a number")?;
+                threads = Some(value.parse::<usize>().ok().filter(|&n| n > 0).ok_or(format!("invalid number of threads '{}'", value))?);
+            },
+            _ => depth = arg.parse::<i32>().map_err(|_| format!("invalid depth '{}'", arg))?
+        }
+    }
+
+    if let Some(threads) = threads {
+        run_scaling(depth, threads);
+        return Ok(());
+    }
+
+    println!("Without move ordering, depth {}:", depth);
+    let unordered = run_set(depth, SearchOptions { move_ordering: false, ..SearchOptions::default() });
+    println!("With move ordering, depth {}:", depth);
+    let ordered = run_set(depth, SearchOptions::default());
+
+    println!("Total nodes: {} without ordering, {} with ordering ({:.1}% of the baseline)", unordered, ordered, ordered as f64 * 100.0 / unordered.max(1) as f64);
+    return Ok(());
+}
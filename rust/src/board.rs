@@ -1,33 +1,70 @@
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(rename_all = "lowercase"))]
 pub enum PieceKind {
     PAWN,
     KING
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(rename_all = "lowercase"))]
 pub enum Player {
     RED,
     BLACK
 }
 
+// CASUAL only forces a capture on the piece that has one, AMERICAN makes any available capture compulsory
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(rename_all = "lowercase"))]
+pub enum RuleVariant {
+    CASUAL,
+    AMERICAN
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Piece {
     pub kind: PieceKind,
     pub player: Player
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Move {
     pub from: (i32, i32),
     pub to: (i32, i32)
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(into = "crate::serialize::BoardRepr", try_from = "crate::serialize::BoardRepr"))]
 pub struct Board {
     pub pieces: Vec<Option<Piece>>,
     pub player_turn: Player,
     pub selected_piece: (i32, i32),
-    pub successive_piece: (i32, i32)
+    pub successive_piece: (i32, i32),
+    pub rules: RuleVariant
+}
+
+// Standard checkers numbering: the 32 dark squares are numbered 1-32 starting from black's side
+pub fn to_square(pos: (i32, i32)) -> i32 {
+    return pos.0 * 4 + pos.1 / 2 + 1;
+}
+
+pub fn from_square(square: i32) -> (i32, i32) {
+    let row = (square - 1) / 4;
+    let col = 2 * ((square - 1) % 4) + (row + 1) % 2;
+    return (row, col);
+}
+
+impl Player {
+    pub fn other(&self) -> Player {
+        return match self {
+            Player::RED => Player::BLACK,
+            Player::BLACK => Player::RED
+        };
+    }
 }
 
 impl Piece {
@@ -46,10 +83,34 @@ impl Move {
             from: (0, 0),
             to: (0, 0) }
     }
+
+    pub fn is_kill(&self) -> bool {
+        return (self.to.0 - self.from.0).abs() == 2;
+    }
+
+    // Square notation, e.g. "11-15" for a step and "15x24" for a jump
+    pub fn to_notation(self) -> String {
+        let separator = if self.is_kill() { "x" } else { "-" };
+        return format!("{}{}{}", to_square(self.from), separator, to_square(self.to));
+    }
+
+    pub fn from_notation(s: &str) -> Result<Move, String> {
+        let squares: Vec<&str> = s.trim().split(['-', 'x']).collect();
+        if squares.len() != 2 {
+            return Err(format!("invalid move '{}'", s));
+        }
+
+        let mut parsed = [0; 2];
+        for (i, square) in squares.iter().enumerate() {
+            parsed[i] = square.parse::<i32>().ok().filter(|n| (1..=32).contains(n)).ok_or(format!("invalid square in move '{}'", s))?;
+        }
+
+        return Ok(Move::new(from_square(parsed[0]), from_square(parsed[1])));
+    }
 }
 
 impl Board {
-    pub fn new() -> Self {
+    pub fn with_rules(rules: RuleVariant) -> Self {
         let mut pieces: Vec<Option<Piece>> = vec![None; 8 * 8];
 
         // Create the black pieces
@@ -74,12 +135,68 @@ impl Board {
             pieces: pieces,
             player_turn: Player::BLACK,
             selected_piece: (-1, -1),
-            successive_piece: (-1, -1)
+            successive_piece: (-1, -1),
+            rules: rules
         }
     }
 
-    pub fn reset(&mut self) {
-        *self = Board::new();
+    // PDN style FEN, e.g. "B:W21,22,K30:B1,2,3" where W is red and a K prefix marks a king
+    pub fn to_fen(&self) -> String {
+        let mut reds: Vec<String> = Vec::new();
+        let mut blacks: Vec<String> = Vec::new();
+
+        for square in 1..=32 {
+            if let Some(piece) = self.at(from_square(square)) {
+                let prefix = if piece.kind == PieceKind::KING { "K" } else { "" };
+                match piece.player {
+                    Player::RED => reds.push(format!("{}{}", prefix, square)),
+                    Player::BLACK => blacks.push(format!("{}{}", prefix, square))
+                }
+            }
+        }
+
+        let turn = if self.player_turn == Player::BLACK { "B" } else { "W" };
+        return format!("{}:W{}:B{}", turn, reds.join(","), blacks.join(","));
+    }
+
+    pub fn from_fen(fen: &str, rules: RuleVariant) -> Result<Board, String> {
+        let mut board = Board::with_rules(rules);
+        board.pieces = vec![None; 8 * 8];
+
+        let fields: Vec<&str> = fen.trim().trim_end_matches('.').split(':').collect();
+        if fields.len() != 3 {
+            return Err(format!("invalid FEN '{}'", fen));
+        }
+
+        board.player_turn = match fields[0] {
+            "B" => Player::BLACK,
+            "W" => Player::RED,
+            _ => return Err(format!("invalid side to move in FEN '{}'", fen))
+        };
+
+        for field in &fields[1..] {
+            let player = match field.chars().next() {
+                Some('B') => Player::BLACK,
+                Some('W') => Player::RED,
+                _ => return Err(format!("invalid piece list in FEN '{}'", fen))
+            };
+
+            for entry in field[1..].split(',').filter(|entry| !entry.is_empty()) {
+                let (kind, number) = match entry.strip_prefix('K') {
+                    Some(number) => (PieceKind::KING, number),
+                    None => (PieceKind::PAWN, entry)
+                };
+
+                let square = number.parse::<i32>().ok().filter(|n| (1..=32).contains(n)).ok_or(format!("invalid square '{}' in FEN", entry))?;
+                let pos = from_square(square);
+                if board.at(pos).is_some() {
+                    return Err(format!("square {} is occupied twice in FEN", square));
+                }
+                board.pieces[pos.0 as usize * 8 + pos.1 as usize] = Some(Piece::new(kind, player));
+            }
+        }
+
+        return Ok(board);
     }
 
     pub fn at(&self, pos: (i32, i32)) -> Option<Piece> {
@@ -202,7 +319,7 @@ impl Board {
         let mut blacks = 0;
 
         // NOTE: Adding this made the bot slower. Check if this is called extensively somewhere
-        if self.get_all_legal_moves(Player::RED).len() == 0 || self.get_all_legal_moves(Player::BLACK).len() == 0 {
+        if self.get_all_legal_moves(Player::RED).is_empty() || self.get_all_legal_moves(Player::BLACK).is_empty() {
             return true;
         }
 
@@ -220,24 +337,44 @@ impl Board {
         return reds == 0 || blacks == 0;
     }
 
+    // Only meaningful once the game is over: whoever has no pieces or no legal move left has lost
     pub fn get_winner(&self) -> Player {
-        let mut blacks = 0;
+        let turn = self.get_turn();
+        if self.get_all_legal_moves(turn).is_empty() {
+            return turn.other();
+        }
+        return turn;
+    }
 
-        for row in 0..8 {
-            for col in 0..8 {
-                if let Some(piece) = self.at((row, col)) {
-                    match piece.player {
-                        Player::BLACK => blacks += 1,
-                        _default => ()
-                    }
-                }
+    // Places or removes a piece with no regard for the rules, for setting up positions
+    pub fn set_piece(&mut self, pos: (i32, i32), piece: Option<Piece>) {
+        self.pieces[pos.0 as usize * 8 + pos.1 as usize] = piece;
+    }
+
+    // Whether a position that was set up by hand could come up in a game and still has moves to play
+    pub fn validate(&self) -> Result<(), String> {
+        for player in [Player::BLACK, Player::RED] {
+            let name = if player == Player::RED { "Red" } else { "Black" };
+            let pieces = self.get_pieces(player);
+            if pieces.is_empty() {
+                return Err(format!("{} has no pieces", name));
+            }
+            if pieces.len() > 12 {
+                return Err(format!("{} has more than 12 pieces", name));
+            }
+
+            // A pawn that reached the far row would have been crowned
+            let promotion_row = if player == Player::RED { 0 } else { 7 };
+            if pieces.iter().any(|&pos| pos.0 == promotion_row && self.at(pos).unwrap().kind == PieceKind::PAWN) {
+                return Err(format!("{} has a pawn on its promotion row", name));
             }
-        }
 
-        if blacks == 0 {
-            return Player::RED;
+            // The game is over as soon as either side is stuck, whoever is to move
+            if self.get_all_legal_moves(player).is_empty() {
+                return Err(format!("{} has no legal moves", name));
+            }
         }
-        return Player::BLACK;
+        return Ok(());
     }
 
     pub fn make_king(&mut self, pos: (i32, i32)) {
@@ -268,7 +405,7 @@ impl Board {
         ];
 
         for &(x_to, y_to, x_mid, y_mid) in &kill_moves {
-            if to == (x_to, y_to) && self.at((x_mid, y_mid)).map_or(false, |piece| piece.player != player) {
+            if to == (x_to, y_to) && self.at((x_mid, y_mid)).is_some_and(|piece| piece.player != player) {
                 return true;
             }
         }
@@ -297,7 +434,7 @@ impl Board {
         ];
 
         for &(x_to, y_to, x_mid, y_mid) in &kill_moves {
-            if to == (x_to, y_to) && self.at((x_mid, y_mid)).map_or(false, |piece| piece.player != player) {
+            if to == (x_to, y_to) && self.at((x_mid, y_mid)).is_some_and(|piece| piece.player != player) {
                 return true;
             }
         }
@@ -395,6 +532,10 @@ impl Board {
             }
         }
 
+        if self.rules == RuleVariant::AMERICAN && moves.iter().any(|m| m.is_kill()) {
+            moves.retain(|m| m.is_kill());
+        }
+
         return moves;
     }
 
@@ -430,3 +571,93 @@ impl Board {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_FEN: &str = "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12";
+
+    fn board(fen: &str) -> Board {
+        return Board::from_fen(fen, RuleVariant::AMERICAN).unwrap();
+    }
+
+    #[test]
+    fn start_position_round_trips() {
+        let start = Board::with_rules(RuleVariant::CASUAL);
+        assert_eq!(start.to_fen(), START_FEN);
+        assert_eq!(Board::from_fen(START_FEN, RuleVariant::CASUAL).unwrap().pieces, start.pieces);
+    }
+
+    #[test]
+    fn kings_round_trip() {
+        let fen = "W:W18,K26:BK3,10";
+        let parsed = board(fen);
+        assert_eq!(parsed.get_turn(), Player::RED);
+        assert_eq!(parsed.at(from_square(26)), Some(Piece::new(PieceKind::KING, Player::RED)));
+        assert_eq!(parsed.at(from_square(3)), Some(Piece::new(PieceKind::KING, Player::BLACK)));
+        assert_eq!(parsed.at(from_square(10)), Some(Piece::new(PieceKind::PAWN, Player::BLACK)));
+        assert_eq!(parsed.to_fen(), fen);
+    }
+
+    #[test]
+    fn malformed_fens_are_rejected() {
+        for fen in [
+            "B:W33:B1",
+            "B:W0:B1",
+            "B:Wx:B1",
+            "B:W5,5:B1",
+            "B:W5:B5",
+            ":W5:B1",
+            "X:W5:B1",
+            "W5:B1",
+            "B:W5:X1"
+        ] {
+            assert!(Board::from_fen(fen, RuleVariant::CASUAL).is_err(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn impossible_positions_fail_validation() {
+        assert!(board(START_FEN).validate().is_ok());
+        // A red pawn on black's back row would have been crowned
+        assert!(board("B:W1,21:B9").validate().is_err());
+        assert!(board("B:W17,18,19,20,21,22,23,24,25,26,27,28,29:B1").validate().is_err());
+        assert!(board("B:W21:B").validate().is_err());
+        // Black's pawn on 28 is stuck behind red's on 32
+        assert!(board("W:W32:B28").validate().is_err());
+    }
+
+    // The side to move loses when it has no pieces or no moves, and wins when the other side is stuck
+    #[test]
+    fn the_stuck_side_loses() {
+        let cases = [
+            // Red has nothing left to move
+            ("W:W:B1", Player::BLACK),
+            ("B:W21:B", Player::RED),
+            // Black's pawn on 28 is blocked by red's on 32, whoever is to move
+            ("B:W32:B28", Player::RED),
+            ("W:W32:B28", Player::RED),
+            // Red's pawn on 5 is blocked by black's on 1
+            ("W:W5:B1", Player::BLACK),
+            ("B:W5:B1", Player::BLACK)
+        ];
+        for (fen, winner) in cases {
+            let position = board(fen);
+            assert!(position.is_game_over(), "{}", fen);
+            assert_eq!(position.get_winner(), winner, "{}", fen);
+        }
+        assert!(!board(START_FEN).is_game_over());
+    }
+
+    #[test]
+    fn move_notation_round_trips() {
+        for notation in ["11-15", "15x24", "1-5", "32-28"] {
+            assert_eq!(Move::from_notation(notation).unwrap().to_notation(), notation);
+        }
+        assert_eq!(Move::from_notation(" 9-13 ").unwrap(), Move::new(from_square(9), from_square(13)));
+        for notation in ["11-33", "0-4", "11", "11-15-19", "a-b", ""] {
+            assert!(Move::from_notation(notation).is_err(), "{}", notation);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::arena::play_game;
+use crate::board::{Board, Move, Player, RuleVariant};
+use crate::bot::{SearchLimits, SearchOptions};
+use crate::engine::{AlphaBeta, Engine};
+use crate::game::Game;
+use crate::pdn::{self, Outcome};
+use crate::rng::Rng;
+use crate::settings::data_dir;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Only this many plies of each game go into the book, every hop of a multi-jump counts
+pub const BOOK_PLIES: usize = 16;
+pub const SELFPLAY_GAMES: usize = 20;
+pub const SELFPLAY_DEPTH: i32 = 8;
+
+// Self-play picks at random between every move scoring within this much of the best one
+const SELFPLAY_MARGIN: f32 = 0.1;
+
+// A small book generated by self-play, used until the user builds their own
+const BUNDLED_BOOK: &str = include_str!("../data/book.txt");
+
+// Moves played from a position are weighted by how the game went for the side that played them
+fn result_weight(outcome: Option<Outcome>, mover: Player) -> u32 {
+    return match outcome {
+        Some(Outcome::WIN(winner)) if winner == mover => 3,
+        Some(Outcome::DRAW) | None => 2,
+        _ => 1
+    };
+}
+
+pub struct Book {
+    entries: HashMap<u64, Vec<(Move, u32)>>
+}
+
+impl Default for Book {
+    fn default() -> Self {
+        return Book::new();
+    }
+}
+
+impl Book {
+    pub fn new() -> Self {
+        Self { entries: HashMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    pub fn add(&mut self, key: u64, m: Move, weight: u32) {
+        let moves = self.entries.entry(key).or_default();
+        match moves.iter_mut().find(|(known, _)| *known == m) {
+            Some((_, total)) => *total += weight,
+            None => moves.push((m, weight))
+        }
+    }
+
+    // Book moves are stored by hash alone, so they are checked against the position before use
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u32)> {
+        let Some(moves) = self.entries.get(&board.hash()) else {
+            return Vec::new();
+        };
+        let legal = board.get_all_legal_moves(board.get_turn());
+        return moves.iter().filter(|(m, _)| legal.contains(m)).copied().collect();
+    }
+
+    // A weighted random choice, so the most successful moves come up most often without always being played
+    pub fn pick(&self, board: &Board, rng: &mut Rng) -> Option<Move> {
+        let moves = self.moves(board);
+        let total: u32 = moves.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut choice = rng.below(total as usize) as u32;
+        for (m, weight) in moves {
+            if choice < weight {
+                return Some(m);
+            }
+            choice -= weight;
+        }
+        return None;
+    }
+
+    fn add_game(&mut self, game: &Game, outcome: Option<Outcome>) {
+        let mut board = game.start.clone();
+        for &m in game.history.iter().take(BOOK_PLIES) {
+            self.add(board.hash(), m, result_weight(outcome, board.get_turn()));
+            board.move_piece(m);
+        }
+    }
+
+    // One "<position hash> <move> <weight>" per line
+    pub fn parse(text: &str) -> Result<Book, String> {
+        let mut book = Book::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 3 {
+                return Err(format!("line {}: expected a hash, a move and a weight", number + 1));
+            }
+            let key = u64::from_str_radix(fields[0], 16).map_err(|_| format!("line {}: invalid hash '{}'", number + 1, fields[0]))?;
+            let m = Move::from_notation(fields[1]).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            let weight = fields[2].parse::<u32>().map_err(|_| format!("line {}: invalid weight '{}'", number + 1, fields[2]))?;
+            book.add(key, m, weight);
+        }
+        return Ok(book);
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut keys: Vec<&u64> = self.entries.keys().collect();
+        keys.sort();
+
+        let mut text = String::from("# Opening book: position hash, move, weight\n");
+        for key in keys {
+            for (m, weight) in &self.entries[key] {
+                text.push_str(&format!("{:016x} {} {}\n", key, m.to_notation(), weight));
+            }
+        }
+        return text;
+    }
+
+    pub fn load(path: &Path) -> Result<Book, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        return Book::parse(&text).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+        }
+        return fs::write(path, self.to_text()).map_err(|e| format!("could not write {}: {}", path.display(), e));
+    }
+
+    // $XDG_DATA_HOME/checkers/book.txt
+    pub fn default_path() -> Option<PathBuf> {
+        return data_dir().map(|dir| dir.join("book.txt"));
+    }
+
+    // The user's own book if they have built one, otherwise the bundled one
+    pub fn load_default() -> Book {
+        if let Some(path) = Book::default_path().filter(|path| path.exists()) {
+            match Book::load(&path) {
+                Ok(book) if !book.is_empty() => return book,
+                Ok(_) => eprintln!("{} is empty, using the bundled opening book", path.display()),
+                Err(e) => eprintln!("{}, using the bundled opening book", e)
+            }
+        }
+        return match Book::parse(BUNDLED_BOOK) {
+            Ok(book) => book,
+            Err(e) => {
+                eprintln!("Not using the bundled opening book: {}", e);
+                Book::new()
+            }
+        };
+    }
+}
+
+// Plays the opening by picking among the moves a search rates close to the best, so the book covers
+// several good lines, then plays the game out to weight those moves by its result
+fn selfplay_game(book: &mut Book, depth: i32, rng: &mut Rng) {
+    let mut engine = AlphaBeta::new(SearchOptions::default());
+    let mut game = Game::new(RuleVariant::CASUAL);
+
+    while game.history.len() < BOOK_PLIES && game.winner().is_none() {
+        let board = &game.board;
+        let sign = if board.get_turn() == Player::RED { 1.0 } else { -1.0 };
+        let scored: Vec<(Move, f32)> = board.get_all_legal_moves(board.get_turn()).into_iter().map(|m| {
+            let mut next = board.clone();
+            next.move_piece(m);
+            engine.set_position(&next);
+            return (m, sign * engine.search(SearchLimits::depth(depth - 1)).score);
+        }).collect();
+
+        let best = scored.iter().map(|(_, score)| *score).fold(f32::NEG_INFINITY, f32::max);
+        let candidates: Vec<Move> = scored.into_iter().filter(|(_, score)| *score >= best - SELFPLAY_MARGIN).map(|(m, _)| m).collect();
+        game.play(candidates[rng.below(candidates.len())]);
+    }
+
+    let (game, winner) = play_game(game, &mut engine, &mut AlphaBeta::new(SearchOptions::default()), depth);
+    book.add_game(&game, Some(winner.map_or(Outcome::DRAW, Outcome::WIN)));
+}
+
+// `checkers book pdn <games.pdn>...`, `checkers book selfplay [--games n] [--depth d]` and `checkers book probe <fen>`.
+// New games are added to the book at --out, the user's book by default.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let usage = "usage: checkers book pdn <games.pdn>... | selfplay [--games n] [--depth d] | probe <fen> [--out book.txt]";
+    let mut out = Book::default_path();
+    let mut games = SELFPLAY_GAMES;
+    let mut depth = SELFPLAY_DEPTH;
+    let mut positional: Vec<&str> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out = Some(PathBuf::from(args.next().ok_or("--out expects a file")?)),
+            "--games" => {
+                let value = args.next().ok_or("--games expects a number")?;
+                games = value.parse().map_err(|_| format!("invalid number of games '{}'", value))?;
+            },
+            "--depth" => {
+                let value = args.next().ok_or("--depth expects a number")?;
+                depth = value.parse().map_err(|_| format!("invalid depth '{}'", value))?;
+            },
+            _ => positional.push(arg)
+        }
+    }
+    let out = out.ok_or("could not find a data directory, use --out")?;
+    let mut book = if out.exists() { Book::load(&out)? } else { Book::new() };
+
+    match positional.split_first() {
+        Some((&"pdn", files)) if !files.is_empty() => {
+            for file in files {
+                for game in pdn::load(Path::new(file))? {
+                    match game {
+                        Ok(game) => book.add_game(&game.game, game.outcome),
+                        Err(e) => eprintln!("Skipping {}: {}", file, e)
+                    }
+                }
+            }
+        },
+        Some((&"selfplay", [])) => {
+            let mut rng = Rng::from_time();
+            for i in 0..games {
+                selfplay_game(&mut book, depth, &mut rng);
+                println!("Game {} of {}, {} positions in the book", i + 1, games, book.len());
+            }
+        },
+        Some((&"probe", [fen])) => {
+            let board = Board::from_fen(fen, RuleVariant::CASUAL)?;
+            let book = if out.exists() { book } else { Book::load_default() };
+            for (m, weight) in book.moves(&board) {
+                println!("{:>6} {}", m.to_notation(), weight);
+            }
+            return Ok(());
+        },
+        _ => return Err(usage.to_string())
+    }
+
+    book.save(&out)?;
+    println!("{} positions written to {}", book.len(), out.display());
+    return Ok(());
+}
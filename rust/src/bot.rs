@@ -1,80 +1,623 @@
-use crate::board::{Board, Move, Player, PieceKind};
+use crate::board::{to_square, Board, Move, Player, PieceKind, RuleVariant};
+use crate::eval::{evaluate, EvalParams};
+use crate::tablebase::{Tablebase, TbValue};
+use crate::tt::{Bound, Entry, TranspositionTable, DEFAULT_TT_ENTRIES};
 
-use std::{thread, time};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
 
-const MINIMAX_DEPTH: i32 = 7; // Increase to make the bot better
+pub const MINIMAX_DEPTH: i32 = 7; // Default difficulty, increase to make the bot better
+pub const MAX_SEARCH_DEPTH: i32 = 64;
 
-pub fn get_bot_move(board: &Board) -> Move {
-    // thread::sleep(time::Duration::from_millis(500));
-    let (_, m) = minimax(board, MINIMAX_DEPTH, -f32::INFINITY, f32::INFINITY);
-    return m.unwrap();
+// A won game scores this minus the number of plies it takes, so quicker wins are preferred
+pub const WIN_SCORE: f32 = 1000.0;
+
+// Only the time is checked every this many nodes, reading the clock is not free
+const NODES_PER_TIME_CHECK: u64 = 1024;
+
+// How many capture plies quiescence search may add beyond the nominal depth, each jump of a multi-jump counts
+const QUIESCENCE_DEPTH: i32 = 16;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SearchLimits {
+    pub depth: i32,
+    pub time: Option<Duration>
 }
 
-pub fn minimax(board: &Board, depth: i32, alpha: f32, beta: f32) -> (f32, Option<Move>) {
-    if board.is_game_over() || depth == 0 {
-        return (state_value(board), None);
+impl SearchLimits {
+    pub fn depth(depth: i32) -> Self {
+        Self { depth: depth, time: None }
     }
 
-    let mut alpha = alpha;
-    let mut beta = beta;
-    let mut best_move = Move::default();
+    pub fn time(time: Duration) -> Self {
+        Self { depth: MAX_SEARCH_DEPTH, time: Some(time) }
+    }
+}
 
-    if board.get_turn() == Player::BLACK {
-        let mut value = f32::INFINITY;
-        for m in board.get_all_legal_moves(Player::BLACK) {
-            let (next_value, _) = minimax(&result(&board, m), depth - 1, alpha, beta);
+#[derive(Clone)]
+pub struct SearchOptions {
+    // Turning this off searches moves in generation order without the transposition table,
+    // which is only useful as a baseline for measuring how much the ordering prunes
+    pub move_ordering: bool,
+    // Keep searching captures past the nominal depth until the position is quiet
+    pub quiescence: bool,
+    // The weights this bot evaluates positions with, which is what gives it its personality
+    pub eval: EvalParams,
+    // Endgame positions found in the tablebase are scored exactly instead of searched
+    pub tablebase: Option<Arc<Tablebase>>,
+    // Helper threads search the same position and share what they find through the transposition table
+    pub threads: usize,
+    // How many of the best moves are searched to the end, each with its own line
+    pub multi_pv: usize
+}
 
-            if next_value < value {
-                best_move = m;
-                value = next_value;
-            }
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self { move_ordering: true, quiescence: true, eval: EvalParams::default(), tablebase: None, threads: 1, multi_pv: 1 }
+    }
+}
 
-            beta = beta.min(value);
-            if beta <= alpha {
-                break;
+#[derive(Clone, Copy, Debug)]
+pub struct SearchResult {
+    // None when the game is already over and there is nothing to play
+    pub best_move: Option<Move>,
+    pub score: f32,
+    pub depth: i32,
+    pub nodes: u64,
+    pub quiescence_nodes: u64,
+    // The reply the search expects, which is what the bot ponders on
+    pub ponder_move: Option<Move>
+}
+
+// One of the moves a search considered, with where it leads
+#[derive(Clone, Debug)]
+pub struct PvLine {
+    pub score: f32,
+    // The moves both sides are expected to play, starting with the move itself
+    pub pv: Vec<Move>
+}
+
+impl PvLine {
+    pub fn pv_text(&self) -> String {
+        return self.pv.iter().map(|m| m.to_notation()).collect::<Vec<String>>().join(" ");
+    }
+}
+
+// What a search reports after every iteration
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    // Best first, there is more than one only in a multi-PV search
+    pub lines: Vec<PvLine>
+}
+
+impl SearchInfo {
+    pub fn nps(&self) -> u64 {
+        return (self.nodes as f64 / self.elapsed.as_secs_f64().max(0.001)) as u64;
+    }
+
+    pub fn score(&self) -> f32 {
+        return self.lines.first().map_or(0.0, |line| line.score);
+    }
+}
+
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "depth {} nodes {} nps {} time {:.2}s", self.depth, self.nodes, self.nps(), self.elapsed.as_secs_f64())?;
+        for (i, line) in self.lines.iter().enumerate() {
+            if self.lines.len() > 1 {
+                write!(f, "\n  {}.", i + 1)?;
             }
+            write!(f, " score {} pv {}", score_text(line.score), line.pv_text())?;
         }
-        return (value, Some(best_move));
-    } else {
-        let mut value = -f32::INFINITY;
-        for m in board.get_all_legal_moves(Player::RED) {
-            let (next_value, _) = minimax(&result(&board, m), depth - 1, alpha, beta);
-
-            if next_value > value {
-                best_move = m;
-                value = next_value;
-            }
+        return Ok(());
+    }
+}
+
+// The player a score says has a forced win, if it is close enough to WIN_SCORE to be one
+pub fn winner_of(score: f32) -> Option<Player> {
+    if WIN_SCORE - score.abs() > MAX_WIN_PLIES {
+        return None;
+    }
+    return Some(if score > 0.0 { Player::RED } else { Player::BLACK });
+}
+
+// Won games are shown as how many plies the win takes
+pub fn score_text(score: f32) -> String {
+    if let Some(winner) = winner_of(score) {
+        let name = if winner == Player::RED { "Red" } else { "Black" };
+        return format!("{} wins in {}", name, (WIN_SCORE - score.abs()).round());
+    }
+    // Rounded first so a tiny negative score does not show up as -0.00
+    let rounded = (score * 100.0).round() / 100.0;
+    return format!("{:+.2}", if rounded == 0.0 { 0.0 } else { rounded });
+}
+
+// No evaluation comes anywhere near this close to WIN_SCORE
+const MAX_WIN_PLIES: f32 = 500.0;
+
+// Lets another thread stop a search or change its limits while it runs, and carries its reports back
+pub struct SearchControl {
+    stop: AtomicBool,
+    // The depth and the deadline, a pondering search only gets its real ones once the predicted move is played
+    limits: Mutex<(i32, Option<Instant>)>,
+    // The result of the deepest iteration finished so far
+    completed: Mutex<Option<SearchResult>>,
+    started: Instant,
+    // Nodes the helper threads have finished counting
+    helper_nodes: AtomicU64,
+    info: Option<Sender<SearchInfo>>
+}
+
+impl SearchControl {
+    pub fn new(limits: SearchLimits) -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            limits: Mutex::new(resolve(limits)),
+            completed: Mutex::new(None),
+            started: Instant::now(),
+            helper_nodes: AtomicU64::new(0),
+            info: None
+        }
+    }
+
+    // Every report is sent to `info` as the search goes
+    pub fn with_info(limits: SearchLimits, info: Sender<SearchInfo>) -> Self {
+        return Self { info: Some(info), ..SearchControl::new(limits) };
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        return self.started.elapsed();
+    }
+
+    // Nobody listening any more is not an error, the search just carries on
+    pub fn report(&self, info: SearchInfo) {
+        if let Some(sender) = &self.info {
+            let _ = sender.send(info);
+        }
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_limits(&self, limits: SearchLimits) {
+        *self.limits.lock().unwrap() = resolve(limits);
+    }
+
+    pub fn completed(&self) -> Option<SearchResult> {
+        return *self.completed.lock().unwrap();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        return self.stop.load(Ordering::Relaxed);
+    }
+
+    fn depth(&self) -> i32 {
+        return self.limits.lock().unwrap().0;
+    }
+
+    pub fn has_deadline(&self) -> bool {
+        return self.limits.lock().unwrap().1.is_some();
+    }
+
+    pub fn past_deadline(&self) -> bool {
+        return self.limits.lock().unwrap().1.is_some_and(|deadline| Instant::now() >= deadline);
+    }
+}
 
-            alpha = alpha.max(value);
-            if beta <= alpha {
+fn resolve(limits: SearchLimits) -> (i32, Option<Instant>) {
+    return (limits.depth, limits.time.map(|time| Instant::now() + time));
+}
+
+// Ordering scores, a move from the transposition table is searched first, then captures, then killers
+const TT_MOVE_SCORE: i32 = 1_000_000;
+const CAPTURE_SCORE: i32 = 100_000;
+const KILLER_SCORE: i32 = 90_000;
+
+struct Search<'a> {
+    options: &'a SearchOptions,
+    control: &'a SearchControl,
+    nodes: u64,
+    quiescence_nodes: u64,
+    aborted: bool,
+    // Set once an iteration has finished, before that there is no move to fall back on
+    has_move: bool,
+    tt: &'a TranspositionTable,
+    // Helpers ignore the deadline and give up as soon as the main thread has finished
+    helper: bool,
+    // Root moves already given a line of their own in a multi-PV search
+    excluded: Vec<Move>,
+    // Two quiet moves per ply that recently caused a cutoff
+    killers: Vec<[Option<Move>; 2]>,
+    // How often each quiet move (from square, to square) has caused a cutoff, weighted by depth
+    history: [[i32; 32]; 32]
+}
+
+// The move the search expects in reply, taken from the transposition table. There is none while the
+// mover is in the middle of a multi-jump.
+fn ponder_move(board: &Board, best_move: Move, tt: &TranspositionTable) -> Option<Move> {
+    let next = result(board, best_move);
+    if next.get_turn() == board.get_turn() || next.is_game_over() {
+        return None;
+    }
+    let m = tt.probe(next.hash())?.best?;
+    return Some(m).filter(|m| next.get_all_legal_moves(next.get_turn()).contains(m));
+}
+
+// The best move followed by the best replies stored in the transposition table, for as long as they are legal
+// and do not go round in a circle
+fn principal_variation(board: &Board, best_move: Move, tt: &TranspositionTable) -> Vec<Move> {
+    let mut pv = vec![best_move];
+    let mut board = result(board, best_move);
+    let mut seen = HashSet::from([board.hash()]);
+    while pv.len() < MAX_SEARCH_DEPTH as usize && !board.is_game_over() {
+        let Some(m) = tt.probe(board.hash()).and_then(|entry| entry.best) else { break };
+        if !board.get_all_legal_moves(board.get_turn()).contains(&m) {
+            break;
+        }
+        board.move_piece(m);
+        if !seen.insert(board.hash()) {
+            break;
+        }
+        pv.push(m);
+    }
+    return pv;
+}
+
+pub fn search_with(board: &Board, control: &SearchControl, options: SearchOptions) -> SearchResult {
+    let (first_limit, deadline) = *control.limits.lock().unwrap();
+    let iterate = options.move_ordering || deadline.is_some();
+    let tt = TranspositionTable::new(if options.move_ordering { DEFAULT_TT_ENTRIES } else { 1 });
+    // Without the table there is nothing for the threads to share
+    let helpers = if options.move_ordering { options.threads.max(1) - 1 } else { 0 };
+
+    // A finished game has no move to find, only its result
+    if board.is_game_over() {
+        return SearchResult { best_move: None, score: win_score(board.get_winner(), 0), depth: 0, nodes: 0, quiescence_nodes: 0, ponder_move: None };
+    }
+
+    return thread::scope(|scope| {
+        // Lazy SMP: every helper runs its own iterative deepening, half of them a ply ahead of the main
+        // thread, and their results only reach the main thread through the entries they leave in the table
+        let handles: Vec<_> = (0..helpers).map(|i| {
+            let (options, tt) = (&options, &tt);
+            return scope.spawn(move || {
+                let mut search = Search::new(options, control, tt, true);
+                let mut reported = 0;
+                for depth in 1 + (i % 2) as i32..=MAX_SEARCH_DEPTH {
+                    search.minimax(board, depth, 0, -f32::INFINITY, f32::INFINITY);
+                    let nodes = search.nodes + search.quiescence_nodes;
+                    control.helper_nodes.fetch_add(nodes - reported, Ordering::Relaxed);
+                    reported = nodes;
+                    if search.aborted {
+                        break;
+                    }
+                }
+                return (search.nodes, search.quiescence_nodes);
+            });
+        }).collect();
+
+        let mut search = Search::new(&options, control, &tt, false);
+
+        // Deepen iteratively so earlier iterations fill the tables that order the later ones. When time
+        // runs out the unfinished iteration is thrown away and the move from the last completed depth is played.
+        let mut depth = if iterate { 1 } else { first_limit };
+        let mut result = SearchResult { best_move: None, score: 0.0, depth: 0, nodes: 0, quiescence_nodes: 0, ponder_move: None };
+        let multi_pv = options.multi_pv.clamp(1, board.get_all_legal_moves(board.get_turn()).len().max(1));
+        while depth <= control.depth() {
+            // Every further line searches the root again without the moves that already have one
+            let mut lines = Vec::new();
+            search.excluded.clear();
+            while lines.len() < multi_pv {
+                let (score, m) = search.minimax(board, depth, 0, -f32::INFINITY, f32::INFINITY);
+                let Some(m) = m.filter(|_| !search.aborted) else {
+                    break;
+                };
+                lines.push(PvLine { score: score, pv: principal_variation(board, m, &tt) });
+                search.excluded.push(m);
+            }
+            if search.aborted || lines.is_empty() {
                 break;
             }
+            result.best_move = Some(lines[0].pv[0]);
+            result.score = lines[0].score;
+            result.depth = depth;
+            result.nodes = search.nodes;
+            result.quiescence_nodes = search.quiescence_nodes;
+            result.ponder_move = ponder_move(board, lines[0].pv[0], &tt);
+            *control.completed.lock().unwrap() = Some(result);
+            control.report(SearchInfo {
+                depth: depth,
+                nodes: search.nodes + search.quiescence_nodes + control.helper_nodes.load(Ordering::Relaxed),
+                elapsed: control.elapsed(),
+                lines: lines
+            });
+            search.has_move = true;
+            depth += 1;
         }
-        return (value, Some(best_move));
-    }
+
+        control.stop();
+        result.nodes = search.nodes;
+        result.quiescence_nodes = search.quiescence_nodes;
+        for handle in handles {
+            let (nodes, quiescence_nodes) = handle.join().unwrap();
+            result.nodes += nodes;
+            result.quiescence_nodes += quiescence_nodes;
+        }
+        return result;
+    });
 }
 
-fn result(board: &Board, m: Move) -> Board {
-    let mut new_board = board.clone();
-    new_board.move_piece(m);
-    return new_board;
+fn captured_value(board: &Board, m: &Move) -> i32 {
+    let middle = ((m.from.0 + m.to.0) / 2, (m.from.1 + m.to.1) / 2);
+    return match board.at(middle).map(|piece| piece.kind) {
+        Some(PieceKind::KING) => 2,
+        Some(PieceKind::PAWN) => 1,
+        None => 0
+    };
 }
 
-pub fn state_value(board: &Board) -> f32 {
-    let mut value: f32 = 0.0;
+// Win scores count the plies from the root, but a position can come up again at another ply, so the
+// table keeps them counted from the position itself
+fn to_tt_score(score: f32, ply: usize) -> f32 {
+    return match winner_of(score) {
+        Some(Player::RED) => score + ply as f32,
+        Some(Player::BLACK) => score - ply as f32,
+        None => score
+    };
+}
+
+fn from_tt_score(score: f32, ply: usize) -> f32 {
+    return match winner_of(score) {
+        Some(Player::RED) => score - ply as f32,
+        Some(Player::BLACK) => score + ply as f32,
+        None => score
+    };
+}
 
-    for row in 0..8 {
-        for col in 0..8 {
-            if let Some(piece) = board.at((row, col)) {
-                match (piece.player, piece.kind) {
-                    (Player::RED, PieceKind::PAWN) => value += 1.0,
-                    (Player::BLACK, PieceKind::PAWN) => value -= 1.0,
-                    (Player::RED, PieceKind::KING) => value += 2.0,
-                    (Player::BLACK, PieceKind::KING) => value -= 2.0,
+fn history_index(m: &Move) -> (usize, usize) {
+    return (to_square(m.from) as usize - 1, to_square(m.to) as usize - 1);
+}
+
+// From red's point of view, like every other score
+fn win_score(winner: Player, plies: usize) -> f32 {
+    let score = WIN_SCORE - plies as f32;
+    return if winner == Player::RED { score } else { -score };
+}
+
+impl<'a> Search<'a> {
+    fn new(options: &'a SearchOptions, control: &'a SearchControl, tt: &'a TranspositionTable, helper: bool) -> Self {
+        Self {
+            options: options,
+            control: control,
+            nodes: 0,
+            quiescence_nodes: 0,
+            aborted: false,
+            has_move: false,
+            tt: tt,
+            helper: helper,
+            excluded: Vec::new(),
+            killers: vec![[None; 2]; MAX_SEARCH_DEPTH as usize + 1],
+            history: [[0; 32]; 32]
+        }
+    }
+
+    fn evaluate(&self, board: &Board) -> f32 {
+        return evaluate(board, &self.options.eval);
+    }
+
+    // Tablebase distances are in whole turns, which is close enough to plies for preferring the quicker win
+    fn probe(&self, board: &Board, ply: usize) -> Option<f32> {
+        let tablebase = self.options.tablebase.as_ref()?;
+        return match tablebase.probe(board)? {
+            TbValue::WIN(distance) => Some(win_score(board.get_turn(), ply + distance as usize)),
+            TbValue::LOSS(distance) => Some(win_score(board.get_turn().other(), ply + distance as usize)),
+            TbValue::DRAW => Some(0.0)
+        };
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        let check_clock = !self.helper && self.has_move && (self.nodes + self.quiescence_nodes).is_multiple_of(NODES_PER_TIME_CHECK);
+        if self.control.is_stopped() || (check_clock && self.control.past_deadline()) {
+            self.aborted = true;
+        }
+        return self.aborted;
+    }
+
+    fn order_moves(&self, board: &Board, moves: &mut [Move], tt_move: Option<Move>, ply: usize) {
+        let killers = self.killers[ply.min(MAX_SEARCH_DEPTH as usize)];
+        moves.sort_by_cached_key(|m| {
+            let score = if Some(*m) == tt_move {
+                TT_MOVE_SCORE
+            } else if m.is_kill() {
+                CAPTURE_SCORE + captured_value(board, m)
+            } else if killers.contains(&Some(*m)) {
+                KILLER_SCORE
+            } else {
+                let (from, to) = history_index(m);
+                self.history[from][to]
+            };
+            return -score;
+        });
+    }
+
+    // A quiet move that refutes a position is likely to refute its siblings too
+    fn record_cutoff(&mut self, m: Move, depth: i32, ply: usize) {
+        if m.is_kill() {
+            return;
+        }
+
+        let killers = &mut self.killers[ply.min(MAX_SEARCH_DEPTH as usize)];
+        if killers[0] != Some(m) {
+            killers[1] = killers[0];
+            killers[0] = Some(m);
+        }
+
+        let (from, to) = history_index(&m);
+        self.history[from][to] += depth * depth;
+    }
+
+    // Only captures are searched here. Unless the side to move is forced to capture, either because it
+    // is in the middle of a multi-jump or because the rules demand it, it may also stand pat and keep
+    // the static evaluation, so quiet positions are evaluated as they are.
+    fn quiesce(&mut self, board: &Board, ply: usize, qply: i32, alpha: f32, beta: f32) -> f32 {
+        self.quiescence_nodes += 1;
+        if board.is_game_over() {
+            return win_score(board.get_winner(), ply);
+        }
+        if self.out_of_time() {
+            return self.evaluate(board);
+        }
+
+        let captures: Vec<Move> = board.get_all_legal_moves(board.get_turn()).into_iter().filter(|m| m.is_kill()).collect();
+        let stand_pat = self.evaluate(board);
+        if captures.is_empty() || qply >= QUIESCENCE_DEPTH {
+            return stand_pat;
+        }
+
+        let forced = board.is_successive() || board.rules == RuleVariant::AMERICAN;
+        let mut alpha = alpha;
+        let mut beta = beta;
+
+        let mut value;
+        if board.get_turn() == Player::BLACK {
+            value = if forced { f32::INFINITY } else { stand_pat };
+            for m in captures {
+                value = value.min(self.quiesce(&result(board, m), ply + 1, qply + 1, alpha, beta));
+                beta = beta.min(value);
+                if beta <= alpha {
+                    break;
+                }
+            }
+        } else {
+            value = if forced { -f32::INFINITY } else { stand_pat };
+            for m in captures {
+                value = value.max(self.quiesce(&result(board, m), ply + 1, qply + 1, alpha, beta));
+                alpha = alpha.max(value);
+                if beta <= alpha {
+                    break;
                 }
             }
         }
+
+        return value;
+    }
+
+    fn minimax(&mut self, board: &Board, depth: i32, ply: usize, alpha: f32, beta: f32) -> (f32, Option<Move>) {
+        self.nodes += 1;
+        if board.is_game_over() {
+            return (win_score(board.get_winner(), ply), None);
+        }
+        if ply > 0 {
+            if let Some(score) = self.probe(board, ply) {
+                return (score, None);
+            }
+        }
+        if depth == 0 {
+            if self.options.quiescence {
+                return (self.quiesce(board, ply, 0, alpha, beta), None);
+            }
+            return (self.evaluate(board), None);
+        }
+
+        if self.out_of_time() {
+            return (0.0, None);
+        }
+
+        let mut alpha = alpha;
+        let mut beta = beta;
+        let mut best_move = Move::default();
+
+        let key = if self.options.move_ordering { board.hash() } else { 0 };
+        let mut tt_move = None;
+        if self.options.move_ordering {
+            if let Some(entry) = self.tt.probe(key) {
+                tt_move = entry.best;
+
+                // The root always searches so it can report a move of the requested depth
+                if ply > 0 && entry.depth >= depth {
+                    let score = from_tt_score(entry.score, ply);
+                    match entry.bound {
+                        Bound::EXACT => return (score, entry.best),
+                        Bound::LOWER if score >= beta => return (score, entry.best),
+                        Bound::UPPER if score <= alpha => return (score, entry.best),
+                        _ => ()
+                    }
+                }
+            }
+        }
+
+        let (alpha_start, beta_start) = (alpha, beta);
+        let mut moves = board.get_all_legal_moves(board.get_turn());
+        if ply == 0 {
+            moves.retain(|m| !self.excluded.contains(m));
+        }
+        if self.options.move_ordering {
+            self.order_moves(board, &mut moves, tt_move, ply);
+        }
+
+        let mut value;
+        if board.get_turn() == Player::BLACK {
+            value = f32::INFINITY;
+            for m in moves {
+                let (next_value, _) = self.minimax(&result(board, m), depth - 1, ply + 1, alpha, beta);
+
+                if next_value < value {
+                    best_move = m;
+                    value = next_value;
+                }
+
+                beta = beta.min(value);
+                if beta <= alpha {
+                    self.record_cutoff(m, depth, ply);
+                    break;
+                }
+            }
+        } else {
+            value = -f32::INFINITY;
+            for m in moves {
+                let (next_value, _) = self.minimax(&result(board, m), depth - 1, ply + 1, alpha, beta);
+
+                if next_value > value {
+                    best_move = m;
+                    value = next_value;
+                }
+
+                alpha = alpha.max(value);
+                if beta <= alpha {
+                    self.record_cutoff(m, depth, ply);
+                    break;
+                }
+            }
+        }
+
+        // Scores are always from red's point of view, so the bounds mean the same at both kinds of node
+        // A root that left moves out has not found the real score of the position
+        if self.options.move_ordering && !self.aborted && (ply > 0 || self.excluded.is_empty()) {
+            let bound = if value <= alpha_start {
+                Bound::UPPER
+            } else if value >= beta_start {
+                Bound::LOWER
+            } else {
+                Bound::EXACT
+            };
+            self.tt.store(Entry { key: key, depth: depth, score: to_tt_score(value, ply), bound: bound, best: Some(best_move) });
+        }
+
+        return (value, Some(best_move));
     }
+}
 
-    return value;
+fn result(board: &Board, m: Move) -> Board {
+    let mut new_board = board.clone();
+    new_board.move_piece(m);
+    return new_board;
 }
@@ -0,0 +1,199 @@
+use crate::board::Player;
+
+use serde::{Deserialize, Serialize};
+
+use std::time::{Duration, Instant};
+
+// All times are in seconds. The increment is added after every turn, while the delay is
+// a grace period at the start of each turn before the clock starts running down.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeControl {
+    pub base: f32,
+    pub increment: f32,
+    pub delay: f32
+}
+
+pub const TIME_CONTROLS: [TimeControl; 6] = [
+    TimeControl { base: 60.0, increment: 0.0, delay: 0.0 },
+    TimeControl { base: 180.0, increment: 2.0, delay: 0.0 },
+    TimeControl { base: 300.0, increment: 0.0, delay: 0.0 },
+    TimeControl { base: 300.0, increment: 0.0, delay: 3.0 },
+    TimeControl { base: 600.0, increment: 5.0, delay: 0.0 },
+    TimeControl { base: 900.0, increment: 10.0, delay: 0.0 }
+];
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        return TIME_CONTROLS[2];
+    }
+}
+
+impl TimeControl {
+    pub fn describe(&self) -> String {
+        let minutes = self.base / 60.0;
+        if self.delay > 0.0 {
+            return format!("{} min, {}s delay", minutes, self.delay);
+        }
+        return format!("{} min + {}s", minutes, self.increment);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Clock {
+    pub control: TimeControl,
+    remaining: [f32; 2],
+    running: Option<Player>,
+    turn_started: Instant,
+    // Time already used this turn before the clock was last paused
+    turn_used: f32,
+    paused: bool
+}
+
+fn index(player: Player) -> usize {
+    return match player {
+        Player::RED => 0,
+        Player::BLACK => 1
+    };
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        return Clock::with_remaining(control, control.base, control.base);
+    }
+
+    pub fn with_remaining(control: TimeControl, red: f32, black: f32) -> Self {
+        Self {
+            control: control,
+            remaining: [red, black],
+            running: None,
+            turn_started: Instant::now(),
+            turn_used: 0.0,
+            paused: false
+        }
+    }
+
+    pub fn running(&self) -> Option<Player> {
+        return self.running;
+    }
+
+    fn turn_elapsed(&self) -> f32 {
+        if self.paused {
+            return self.turn_used;
+        }
+        return self.turn_used + self.turn_started.elapsed().as_secs_f32();
+    }
+
+    pub fn remaining(&self, player: Player) -> f32 {
+        let mut remaining = self.remaining[index(player)];
+        if self.running == Some(player) {
+            remaining -= (self.turn_elapsed() - self.control.delay).max(0.0);
+        }
+        return remaining.max(0.0);
+    }
+
+    pub fn start(&mut self, player: Player) {
+        self.running = Some(player);
+        self.turn_started = Instant::now();
+        self.turn_used = 0.0;
+        self.paused = false;
+    }
+
+    // Ends the running player's turn, crediting the increment, and starts the other player's clock
+    pub fn switch(&mut self, to: Player) {
+        if let Some(player) = self.running {
+            self.remaining[index(player)] = self.remaining(player) + self.control.increment;
+        }
+        self.start(to);
+    }
+
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.turn_used = self.turn_elapsed();
+            self.paused = true;
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.turn_started = Instant::now();
+            self.paused = false;
+        }
+    }
+
+    pub fn flagged(&self) -> Option<Player> {
+        return self.running.filter(|&player| self.remaining(player) <= 0.0);
+    }
+
+    // How long the bot may think: a slice of what is left plus most of what it gets back after moving
+    pub fn budget(&self, player: Player) -> Duration {
+        let remaining = self.remaining(player);
+        let budget = remaining / 25.0 + self.control.increment * 0.8 + self.control.delay * 0.8;
+        return Duration::from_secs_f32(budget.min(remaining * 0.5).max(0.01));
+    }
+}
+
+pub fn format_time(seconds: f32) -> String {
+    if seconds < 10.0 {
+        return format!("0:{:04.1}", seconds);
+    }
+    let seconds = seconds.ceil() as i32;
+    return format!("{}:{:02}", seconds / 60, seconds % 60);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    const BLITZ: TimeControl = TimeControl { base: 60.0, increment: 5.0, delay: 0.0 };
+
+    #[test]
+    fn running_out_of_time_flags_the_player() {
+        let mut clock = Clock::with_remaining(BLITZ, 0.05, 60.0);
+        assert_eq!(clock.flagged(), None);
+        clock.start(Player::RED);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(clock.remaining(Player::RED), 0.0);
+        assert_eq!(clock.flagged(), Some(Player::RED));
+        // The waiting player's time is not touched
+        assert_eq!(clock.remaining(Player::BLACK), 60.0);
+    }
+
+    #[test]
+    fn the_increment_is_added_after_each_turn() {
+        let mut clock = Clock::with_remaining(BLITZ, 10.0, 10.0);
+        clock.start(Player::RED);
+        clock.switch(Player::BLACK);
+        let red = clock.remaining(Player::RED);
+        assert!(red > 14.9 && red <= 15.0, "{}", red);
+        assert_eq!(clock.running(), Some(Player::BLACK));
+
+        clock.switch(Player::RED);
+        let black = clock.remaining(Player::BLACK);
+        assert!(black > 14.9 && black <= 15.0, "{}", black);
+    }
+
+    #[test]
+    fn a_paused_clock_stands_still() {
+        let mut clock = Clock::with_remaining(BLITZ, 10.0, 10.0);
+        clock.start(Player::RED);
+        clock.pause();
+        let before = clock.remaining(Player::RED);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(clock.remaining(Player::RED), before);
+        clock.resume();
+        thread::sleep(Duration::from_millis(50));
+        assert!(clock.remaining(Player::RED) < before);
+    }
+
+    #[test]
+    fn the_delay_runs_before_the_clock() {
+        let control = TimeControl { base: 60.0, increment: 0.0, delay: 3.0 };
+        let mut clock = Clock::with_remaining(control, 10.0, 10.0);
+        clock.start(Player::RED);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(clock.remaining(Player::RED), 10.0);
+    }
+}
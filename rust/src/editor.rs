@@ -0,0 +1,164 @@
+use crate::board::{Board, Piece, PieceKind, Player};
+use crate::settings::Settings;
+use crate::theme::Theme;
+use crate::{draw_labels, draw_piece, draw_pieces, draw_tiles, to_view, PANEL_WIDTH};
+
+use raylib::prelude::*;
+
+const PALETTE: [(PieceKind, Player); 4] = [
+    (PieceKind::PAWN, Player::RED),
+    (PieceKind::KING, Player::RED),
+    (PieceKind::PAWN, Player::BLACK),
+    (PieceKind::KING, Player::BLACK)
+];
+
+pub enum EditorAction {
+    PLAY(Board),
+    ANALYSE(Board),
+    CANCEL
+}
+
+// Sets up a position piece by piece, or from a FEN on the clipboard
+pub struct Editor {
+    board: Board,
+    // The index into PALETTE of the piece a click places
+    selected: usize,
+    // Why the position was turned down, or that the FEN was copied
+    message: Option<String>
+}
+
+fn palette_button(settings: &Settings, index: usize) -> Rectangle {
+    let size = (PANEL_WIDTH - 40) / 4;
+    return Rectangle::new((settings.board_size + 20 + index as i32 * size) as f32, 70.0, size as f32, size as f32);
+}
+
+impl Editor {
+    pub fn new(board: &Board) -> Self {
+        let mut board = board.clone();
+        board.deselect();
+        board.unset_successive();
+        Self { board: board, selected: 0, message: None }
+    }
+
+    fn clear(&mut self) {
+        for square in 0..64 {
+            self.board.set_piece((square / 8, square % 8), None);
+        }
+    }
+
+    // The position is only let go once it is one a game can be played from
+    fn finish(&mut self, analyse: bool) -> Option<EditorAction> {
+        if let Err(e) = self.board.validate() {
+            self.message = Some(e);
+            return None;
+        }
+        let board = self.board.clone();
+        return Some(if analyse { EditorAction::ANALYSE(board) } else { EditorAction::PLAY(board) });
+    }
+
+    pub fn update(&mut self, rl: &mut RaylibHandle, settings: &Settings) -> Option<EditorAction> {
+        let control = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        if control && rl.is_key_pressed(KeyboardKey::KEY_C) {
+            self.message = Some(match rl.set_clipboard_text(&self.board.to_fen()) {
+                Ok(()) => "FEN copied".to_string(),
+                Err(e) => format!("Could not copy the FEN: {}", e)
+            });
+        } else if control && rl.is_key_pressed(KeyboardKey::KEY_V) {
+            let pasted = rl.get_clipboard_text().map_err(|e| e.to_string()).and_then(|text| Board::from_fen(&text, settings.rules));
+            match pasted {
+                Ok(board) => {
+                    self.board = board;
+                    self.message = None;
+                },
+                Err(e) => self.message = Some(e)
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_C) {
+            self.clear();
+        } else if rl.is_key_pressed(KeyboardKey::KEY_R) {
+            self.board = Board::with_rules(settings.rules);
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_T) {
+            self.board.player_turn = self.board.get_turn().other();
+        }
+        for (i, key) in [KeyboardKey::KEY_ONE, KeyboardKey::KEY_TWO, KeyboardKey::KEY_THREE, KeyboardKey::KEY_FOUR].iter().enumerate() {
+            if rl.is_key_pressed(*key) {
+                self.selected = i;
+            }
+        }
+
+        let mouse = rl.get_mouse_position();
+        let tile_size = (settings.board_size / 8) as f32;
+        let pos = to_view(settings, ((mouse.y / tile_size).floor() as i32, (mouse.x / tile_size).floor() as i32));
+        let on_board = mouse.x < settings.board_size as f32 && (pos.0 + pos.1) % 2 == 1;
+
+        if rl.is_mouse_button_pressed(raylib::consts::MouseButton::MOUSE_BUTTON_LEFT) {
+            if let Some(index) = (0..PALETTE.len()).find(|&i| palette_button(settings, i).check_collision_point_rec(mouse)) {
+                self.selected = index;
+            } else if on_board {
+                // Clicking the piece that would be placed takes it off again
+                let (kind, player) = PALETTE[self.selected];
+                let piece = Piece::new(kind, player);
+                let current = self.board.at(pos);
+                let same = current.is_some_and(|current| current.kind == kind && current.player == player);
+                self.board.set_piece(pos, if same { None } else { Some(piece) });
+                self.message = None;
+            }
+        }
+        if on_board && rl.is_mouse_button_pressed(raylib::consts::MouseButton::MOUSE_BUTTON_RIGHT) {
+            self.board.set_piece(pos, None);
+            self.message = None;
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            return self.finish(false);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_A) {
+            return self.finish(true);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_E) {
+            return Some(EditorAction::CANCEL);
+        }
+        return None;
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, theme: &Theme, settings: &Settings) {
+        draw_tiles(d, &self.board, theme, settings, None);
+        draw_labels(d, theme, settings);
+        draw_pieces(d, &self.board, theme, settings, &None);
+
+        let x = settings.board_size + 20;
+        let text = theme.dark_square;
+        d.draw_rectangle(settings.board_size, 0, PANEL_WIDTH, settings.board_size, theme.light_square);
+        d.draw_text("Editor", x, 20, 30, text);
+
+        for (i, &(kind, player)) in PALETTE.iter().enumerate() {
+            let rect = palette_button(settings, i);
+            d.draw_rectangle_rec(rect, if i == self.selected { theme.highlight } else { theme.dark_square });
+            let (x, y) = ((rect.x + rect.width / 2.0) as i32, (rect.y + rect.height / 2.0) as i32);
+            draw_piece(d, Piece::new(kind, player), theme, rect.width * 0.35, x, y);
+        }
+
+        let turn = if self.board.get_turn() == Player::RED { "Red to move" } else { "Black to move" };
+        d.draw_text(turn, x, 140, 20, text);
+
+        let help = [
+            "1-4: choose a piece",
+            "Click: place or remove",
+            "Right click: remove",
+            "T: change side to move",
+            "C: clear, R: start position",
+            "Ctrl+C / Ctrl+V: FEN",
+            "Enter: play from here",
+            "A: analyse from here",
+            "E: back to the game"
+        ];
+        for (i, line) in help.iter().enumerate() {
+            d.draw_text(line, x, 190 + i as i32 * 24, 18, text);
+        }
+
+        if let Some(message) = &self.message {
+            d.draw_text(message, x, settings.board_size - 40, 18, theme.error);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::board::{Board, RuleVariant};
+use crate::bot::{self, PvLine, SearchControl, SearchInfo, SearchLimits, SearchOptions, SearchResult};
+use crate::mcts::{self, MctsOptions};
+use crate::rng::Rng;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineKind {
+    ALPHABETA,
+    MCTS,
+    RANDOM
+}
+
+impl EngineKind {
+    pub fn parse(s: &str) -> Option<EngineKind> {
+        return match s {
+            "alphabeta" => Some(EngineKind::ALPHABETA),
+            "mcts" => Some(EngineKind::MCTS),
+            "random" => Some(EngineKind::RANDOM),
+            _ => None
+        };
+    }
+}
+
+// Everything the game and the tools need from a bot, so they work with any of them
+pub trait Engine: Send {
+    fn name(&self) -> String;
+
+    // Forgets whatever the engine kept from the previous game
+    fn new_game(&mut self) {}
+
+    fn set_position(&mut self, board: &Board);
+
+    // Searches until the limits in the control are reached or another thread stops it through the control.
+    // Progress is reported through the control as the search goes.
+    fn search_with(&mut self, control: &SearchControl) -> SearchResult;
+
+    fn search(&mut self, limits: SearchLimits) -> SearchResult {
+        return self.search_with(&SearchControl::new(limits));
+    }
+}
+
+pub struct AlphaBeta {
+    pub options: SearchOptions,
+    board: Board
+}
+
+impl AlphaBeta {
+    pub fn new(options: SearchOptions) -> Self {
+        Self { options: options, board: Board::with_rules(RuleVariant::CASUAL) }
+    }
+}
+
+impl Engine for AlphaBeta {
+    fn name(&self) -> String {
+        return "alpha-beta".to_string();
+    }
+
+    fn set_position(&mut self, board: &Board) {
+        self.board = board.clone();
+    }
+
+    fn search_with(&mut self, control: &SearchControl) -> SearchResult {
+        return bot::search_with(&self.board, control, self.options.clone());
+    }
+}
+
+pub struct MonteCarlo {
+    pub options: MctsOptions,
+    board: Board
+}
+
+impl MonteCarlo {
+    pub fn new(options: MctsOptions) -> Self {
+        Self { options: options, board: Board::with_rules(RuleVariant::CASUAL) }
+    }
+}
+
+impl Engine for MonteCarlo {
+    fn name(&self) -> String {
+        return "mcts".to_string();
+    }
+
+    fn set_position(&mut self, board: &Board) {
+        self.board = board.clone();
+    }
+
+    fn search_with(&mut self, control: &SearchControl) -> SearchResult {
+        return mcts::search_with(&self.board, control, &self.options);
+    }
+}
+
+// Plays any legal move, the weakest opponent there is and a baseline for the others
+pub struct RandomMover {
+    rng: Rng,
+    board: Board
+}
+
+impl Default for RandomMover {
+    fn default() -> Self {
+        return RandomMover::new();
+    }
+}
+
+impl RandomMover {
+    pub fn new() -> Self {
+        Self { rng: Rng::from_time(), board: Board::with_rules(RuleVariant::CASUAL) }
+    }
+}
+
+impl Engine for RandomMover {
+    fn name(&self) -> String {
+        return "random".to_string();
+    }
+
+    fn set_position(&mut self, board: &Board) {
+        self.board = board.clone();
+    }
+
+    fn search_with(&mut self, control: &SearchControl) -> SearchResult {
+        let moves = self.board.get_all_legal_moves(self.board.get_turn());
+        let best_move = if moves.is_empty() { None } else { Some(moves[self.rng.below(moves.len())]) };
+        let lines = best_move.map(|m| PvLine { score: 0.0, pv: vec![m] }).into_iter().collect();
+        control.report(SearchInfo { depth: 0, nodes: 1, elapsed: control.elapsed(), lines: lines });
+        return SearchResult { best_move: best_move, score: 0.0, depth: 0, nodes: 1, quiescence_nodes: 0, ponder_move: None };
+    }
+}
+
+pub fn create(kind: EngineKind, options: SearchOptions, mcts: MctsOptions) -> Box<dyn Engine> {
+    return match kind {
+        EngineKind::ALPHABETA => Box::new(AlphaBeta::new(options)),
+        EngineKind::MCTS => Box::new(MonteCarlo::new(mcts)),
+        EngineKind::RANDOM => Box::new(RandomMover::new())
+    };
+}
@@ -0,0 +1,272 @@
+use crate::board::{to_square, Board, PieceKind, Player, RuleVariant};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+
+// All weights are in pawns and every term is scored from red's point of view, like the search.
+// A weights file may leave out any field, it then keeps its default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalParams {
+    pub pawn: f32,
+    pub king: f32,
+    // Pawns still guarding their own king row
+    pub back_rank: f32,
+    // Pieces on the eight central squares
+    pub centre: f32,
+    // Per row a pawn has advanced
+    pub advancement: f32,
+    // Pawns with no piece left between them and the king row
+    pub runaway: f32,
+    // Per legal move
+    pub mobility: f32,
+    // Kings that cannot move at all
+    pub trapped_king: f32,
+    // A pawn stuck in the opponent's corner behind one of its back rank pawns
+    pub dog_hole: f32,
+    // Below this many pieces the endgame terms take over from the back rank
+    pub endgame_pieces: usize,
+    // Reward for the side ahead in material as pieces come off
+    pub endgame_trade: f32,
+    // Per square between the stronger side's kings and the nearest enemy piece
+    pub endgame_distance: f32,
+    // Bonus per square 1-32 as seen from red's side of the board, black's pieces use the mirrored square
+    pub pawn_table: [f32; 32],
+    pub king_table: [f32; 32]
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self {
+            pawn: 1.0,
+            king: 1.5,
+            back_rank: 0.1,
+            centre: 0.05,
+            advancement: 0.02,
+            runaway: 0.5,
+            mobility: 0.02,
+            trapped_king: 0.4,
+            dog_hole: 0.15,
+            endgame_pieces: 8,
+            endgame_trade: 1.0,
+            endgame_distance: 0.02,
+            pawn_table: [0.0; 32],
+            king_table: [0.0; 32]
+        }
+    }
+}
+
+impl EvalParams {
+    // TOML by default, JSON when the file ends in .json and the serialize feature is enabled
+    pub fn load(path: &Path) -> Result<EvalParams, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+
+        if path.extension().is_some_and(|extension| extension == "json") {
+            #[cfg(feature = "serialize")]
+            return serde_json::from_str(&text).map_err(|e| format!("invalid weights file {}: {}", path.display(), e));
+            #[cfg(not(feature = "serialize"))]
+            return Err(format!("cannot read {}, JSON weights need the serialize feature", path.display()));
+        }
+
+        return toml::from_str(&text).map_err(|e| format!("invalid weights file {}: {}", path.display(), e.message()));
+    }
+}
+
+const CENTRE: [(i32, i32); 8] = [(2, 3), (2, 5), (3, 2), (3, 4), (4, 3), (4, 5), (5, 2), (5, 4)];
+
+fn sign(player: Player) -> f32 {
+    return match player {
+        Player::RED => 1.0,
+        Player::BLACK => -1.0
+    };
+}
+
+fn index(player: Player) -> usize {
+    return match player {
+        Player::RED => 0,
+        Player::BLACK => 1
+    };
+}
+
+fn forward(player: Player) -> i32 {
+    return match player {
+        Player::RED => -1,
+        Player::BLACK => 1
+    };
+}
+
+fn king_row(player: Player) -> i32 {
+    return match player {
+        Player::RED => 0,
+        Player::BLACK => 7
+    };
+}
+
+// A pawn is a runaway when the cone in front of it, every square it could possibly pass on its way to
+// the king row, is empty. Nothing can then get in front of it in time.
+fn is_runaway(board: &Board, pos: (i32, i32), player: Player) -> bool {
+    let dir = forward(player);
+    let distance = (king_row(player) - pos.0).abs();
+
+    for step in 1..=distance {
+        let row = pos.0 + dir * step;
+        for col in (pos.1 - step)..=(pos.1 + step) {
+            if board.at((row, col)).is_some() {
+                return false;
+            }
+        }
+    }
+
+    return true;
+}
+
+// The dog-holes are squares 5 and 28: a pawn there can only move to the corner, so the opponent's
+// pawn on that corner (square 1 or 32) leaves it stuck
+fn in_dog_hole(board: &Board, pos: (i32, i32), player: Player) -> bool {
+    let (hole, corner) = match player {
+        Player::RED => ((1, 0), (0, 1)),
+        Player::BLACK => ((6, 7), (7, 6))
+    };
+    return pos == hole && board.at(corner).is_some_and(|piece| piece.player != player);
+}
+
+fn table_index(pos: (i32, i32), player: Player) -> usize {
+    let square = to_square(pos) as usize - 1;
+    return match player {
+        Player::RED => square,
+        Player::BLACK => 31 - square
+    };
+}
+
+fn distance(a: (i32, i32), b: (i32, i32)) -> i32 {
+    return (a.0 - b.0).abs().max((a.1 - b.1).abs());
+}
+
+pub fn evaluate(board: &Board, params: &EvalParams) -> f32 {
+    let mut value = 0.0;
+    let mut material = [0.0, 0.0];
+    let mut pieces = 0;
+
+    for row in 0..8 {
+        for col in 0..8 {
+            if let Some(piece) = board.at((row, col)) {
+                let s = sign(piece.player);
+                pieces += 1;
+
+                // Mobility is counted per piece, so it stays meaningful in the middle of a multi-jump
+                let moves = board.get_legal_moves((row, col)).len();
+                value += s * params.mobility * moves as f32;
+
+                if CENTRE.contains(&(row, col)) {
+                    value += s * params.centre;
+                }
+
+                match piece.kind {
+                    PieceKind::KING => {
+                        material[index(piece.player)] += params.king;
+                        value += s * params.king_table[table_index((row, col), piece.player)];
+                        if moves == 0 {
+                            value -= s * params.trapped_king;
+                        }
+                    },
+                    PieceKind::PAWN => {
+                        material[index(piece.player)] += params.pawn;
+                        value += s * params.pawn_table[table_index((row, col), piece.player)];
+                        let advanced = (row - king_row(piece.player.other())).abs();
+                        value += s * params.advancement * advanced as f32;
+                        if is_runaway(board, (row, col), piece.player) {
+                            value += s * params.runaway;
+                        }
+                        if in_dog_hole(board, (row, col), piece.player) {
+                            value -= s * params.dog_hole;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    value += material[0] - material[1];
+
+    if pieces > params.endgame_pieces {
+        for player in [Player::RED, Player::BLACK] {
+            let row = king_row(player.other());
+            for col in 0..8 {
+                if board.at((row, col)).is_some_and(|piece| piece.player == player && piece.kind == PieceKind::PAWN) {
+                    value += sign(player) * params.back_rank;
+                }
+            }
+        }
+        return value;
+    }
+
+    // In the endgame the side ahead wants to trade down and to bring its kings to the enemy pieces
+    let ahead = material[0] - material[1];
+    if ahead != 0.0 {
+        let stronger = if ahead > 0.0 { Player::RED } else { Player::BLACK };
+        value += params.endgame_trade * ahead / pieces as f32;
+
+        let enemies = board.get_pieces(stronger.other());
+        for pos in board.get_pieces(stronger) {
+            if board.at(pos).unwrap().kind == PieceKind::KING {
+                let nearest = enemies.iter().map(|&enemy| distance(pos, enemy)).min().unwrap_or(0);
+                value -= sign(stronger) * params.endgame_distance * nearest as f32;
+            }
+        }
+    }
+
+    return value;
+}
+
+// `checkers eval [--weights file] <fen>` prints the evaluation of a position
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut params = EvalParams::default();
+    let mut args = args;
+    if args.first().map(|arg| arg.as_str()) == Some("--weights") {
+        let path = args.get(1).ok_or("--weights expects a file")?;
+        params = EvalParams::load(Path::new(path))?;
+        args = &args[2..];
+    }
+
+    let fen = args.first().ok_or("usage: checkers eval [--weights file] <fen>")?;
+    let board = Board::from_fen(fen, RuleVariant::CASUAL)?;
+    println!("{:.3}", evaluate(&board, &params));
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Positions where it is clear which side is better, and the evaluation has to agree
+    const LABELLED_POSITIONS: [(&str, Player); 8] = [
+        // Red is a full pawn up
+        ("B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11", Player::RED),
+        // Black's pawn on 20 has a free run to the king row, red's pawns are still held up by the one on 1
+        ("W:W21,22:B1,20", Player::BLACK),
+        // Equal material, but red's king on 29 is boxed in by the pawns on 22 and 25
+        ("B:WK29,K32:B1,22,25", Player::BLACK),
+        // Red's pawn on 5 is stuck in the dog-hole behind the black pawn on 1
+        ("B:W5,30:B1,3", Player::BLACK),
+        // Black's back rank is intact while red's is gone
+        ("B:W17,18,19,20,21,22,23,24,25,26:B1,2,3,4,9,10,11,12,13,14", Player::BLACK),
+        // A king against a pawn
+        ("W:WK18:B12", Player::RED),
+        // Two kings against one
+        ("B:WK1,K27:BK14", Player::RED),
+        // Black controls the centre with the same material
+        ("W:W21,22,25,26,29,30:B10,11,14,15,18,19", Player::BLACK)
+    ];
+
+    #[test]
+    fn labelled_positions_favour_the_better_side() {
+        let params = EvalParams::default();
+        for (fen, better) in LABELLED_POSITIONS {
+            let board = Board::from_fen(fen, RuleVariant::CASUAL).unwrap();
+            let score = evaluate(&board, &params);
+            assert!(score * sign(better) > 0.0, "{} scores {:.3} but {:?} is better", fen, score, better);
+        }
+    }
+}
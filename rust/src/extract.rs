@@ -0,0 +1,200 @@
+use crate::board::{Board, Move, Player, RuleVariant};
+use crate::bot::{winner_of, PvLine, SearchControl, SearchLimits, SearchOptions};
+use crate::engine::{self, Engine, EngineKind};
+use crate::mcts::MctsOptions;
+use crate::pdn;
+use crate::puzzle::{self, is_quiet, line_text, material_balance, material_won, Puzzle};
+use crate::tablebase;
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Arc;
+
+pub const EXTRACT_DEPTH: i32 = 10;
+
+// A quick search picks out the positions worth the full one, those where the side to move is doing
+// better than the piece count says
+const SCAN_DEPTH: i32 = 6;
+const SCAN_MARGIN: f32 = 0.8;
+
+// How far, in pawns, the winning move has to be ahead of the next best one. Later moves of the line
+// only need to be clearly better, the puzzle is already decided by then.
+const FIRST_MOVE_GAP: f32 = 1.0;
+const LATER_MOVE_GAP: f32 = 0.5;
+
+// The opening is left out, and so are lines too long to be a puzzle
+const SKIP_OPENING_PLIES: usize = 6;
+const MAX_LINE_PLIES: usize = 16;
+
+// `multi` is an engine set up to report its two best lines
+fn best_lines(board: &Board, depth: i32, multi: &mut dyn Engine) -> Vec<PvLine> {
+    let (sender, receiver) = mpsc::channel();
+    let control = SearchControl::with_info(SearchLimits::depth(depth), sender);
+    multi.set_position(board);
+    multi.search_with(&control);
+    return receiver.try_iter().last().map_or(Vec::new(), |info| info.lines);
+}
+
+// The only move that wins, if there is one. A win has to be the only win, anything else has to beat
+// the next best move by `gap`.
+fn only_winning_move(board: &Board, depth: i32, gap: f32, multi: &mut dyn Engine) -> Option<Move> {
+    let lines = best_lines(board, depth, multi);
+    let best = lines.first()?;
+    let Some(second) = lines.get(1) else {
+        return best.pv.first().copied();
+    };
+
+    let mover = board.get_turn();
+    if winner_of(best.score) == Some(mover) {
+        return if winner_of(second.score) == Some(mover) { None } else { best.pv.first().copied() };
+    }
+    let sign = if mover == Player::RED { 1.0 } else { -1.0 };
+    return if sign * (best.score - second.score) >= gap { best.pv.first().copied() } else { None };
+}
+
+// Plays the winning line out until the win or the material is in hand, with the engine's best defence
+// in between. Captures are forced, so a position whose first move is one is no puzzle.
+fn find_puzzle(board: &Board, depth: i32, engine: &mut dyn Engine, multi: &mut dyn Engine) -> Option<Puzzle> {
+    let mover = board.get_turn();
+    if board.get_all_legal_moves(mover).len() < 2 || !is_quiet(board) {
+        return None;
+    }
+
+    let first = only_winning_move(board, depth, FIRST_MOVE_GAP, multi)?;
+    let mut current = board.clone();
+    let mut solution = Vec::new();
+    while solution.len() < MAX_LINE_PLIES && !current.is_game_over() {
+        let m = if solution.is_empty() {
+            first
+        } else if current.get_turn() != mover {
+            engine.set_position(&current);
+            engine.search(SearchLimits::depth(depth)).best_move?
+        } else {
+            let moves = current.get_all_legal_moves(mover);
+            if moves.len() == 1 { moves[0] } else { only_winning_move(&current, depth, LATER_MOVE_GAP, multi)? }
+        };
+        current.move_piece(m);
+        solution.push(m);
+        if current.get_turn() != mover && is_quiet(&current) && material_won(board, &current, mover) >= 1 {
+            break;
+        }
+    }
+
+    let side = if mover == Player::RED { "Red" } else { "Black" };
+    let won = material_won(board, &current, mover);
+    let title = if current.is_game_over() && current.get_winner() == mover {
+        format!("{} to play and win", side)
+    } else if current.get_turn() != mover && is_quiet(&current) && won >= 1 {
+        format!("{} to play and win {}", side, if won == 1 { "a piece".to_string() } else { format!("{} pieces", won) })
+    } else {
+        return None;
+    };
+    return Some(Puzzle { title: title, start: board.clone(), solution: solution });
+}
+
+// The positions of every game in a PDN file, or of a positions file as written by `checkers arena --dump`
+fn load_positions(path: &Path) -> Result<Vec<Board>, String> {
+    let mut positions = Vec::new();
+    if path.extension().is_some_and(|extension| extension == "pdn") {
+        for game in pdn::load(path)? {
+            match game {
+                Ok(game) => positions.extend(game.game.positions().into_iter().skip(SKIP_OPENING_PLIES)),
+                Err(e) => eprintln!("Skipping {}: {}", path.display(), e)
+            }
+        }
+        return Ok(positions);
+    }
+
+    let text = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // The result at the end of the line is not needed
+        let fen = line.rsplit_once(' ').map_or(line, |(fen, _)| fen);
+        positions.push(Board::from_fen(fen, RuleVariant::AMERICAN).map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))?);
+    }
+    return Ok(positions);
+}
+
+// `checkers extract [--depth d] [--engine alphabeta|mcts|random] [--out pack.txt] <games.pdn|positions.txt>...`
+// searches played games for positions with only one winning move and adds them to a puzzle pack, by default
+// the one in the puzzles directory that puzzle mode reads
+pub fn run(args: &[String]) -> Result<(), String> {
+    let usage = "usage: checkers extract [--depth d] [--engine alphabeta|mcts|random] [--out pack.txt] <games.pdn|positions.txt>...";
+    let mut depth = EXTRACT_DEPTH;
+    let mut kind = EngineKind::ALPHABETA;
+    let mut out: Option<PathBuf> = None;
+    let mut files: Vec<&str> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => {
+                let value = args.next().ok_or("--depth expects a number")?;
+                depth = value.parse().ok().filter(|&depth| depth > 0).ok_or(format!("invalid depth '{}'", value))?;
+            },
+            "--engine" => {
+                let value = args.next().ok_or("--engine expects alphabeta, mcts or random")?;
+                kind = EngineKind::parse(value).ok_or(format!("unknown engine '{}'", value))?;
+            },
+            "--out" => out = Some(PathBuf::from(args.next().ok_or("--out expects a file")?)),
+            _ => files.push(arg)
+        }
+    }
+    if files.is_empty() {
+        return Err(usage.to_string());
+    }
+    let out = match out {
+        Some(out) => out,
+        None => puzzle::packs_dir().ok_or("could not find a data directory, use --out")?.join("extracted.txt")
+    };
+
+    // Positions already in a pack are not added again
+    let mut seen: HashSet<String> = puzzle::load_all().iter().map(|puzzle| puzzle.start.to_fen()).collect();
+    if out.exists() {
+        seen.extend(puzzle::load(&out)?.iter().map(|puzzle| puzzle.start.to_fen()));
+    }
+
+    let options = SearchOptions { tablebase: tablebase::load_default().map(Arc::new), ..SearchOptions::default() };
+    let mut engine = engine::create(kind, options.clone(), MctsOptions::default());
+    let mut multi = engine::create(kind, SearchOptions { multi_pv: 2, ..options }, MctsOptions { multi_pv: 2, ..MctsOptions::default() });
+    let mut found = Vec::new();
+    let mut scanned = 0;
+    for file in &files {
+        for board in load_positions(Path::new(file))? {
+            if board.is_game_over() || board.is_successive() || !seen.insert(board.to_fen()) {
+                continue;
+            }
+            scanned += 1;
+
+            let mover = board.get_turn();
+            let sign = if mover == Player::RED { 1.0 } else { -1.0 };
+            let balance = material_balance(&board, mover) as f32;
+            engine.set_position(&board);
+            if sign * engine.search(SearchLimits::depth(SCAN_DEPTH)).score - balance < SCAN_MARGIN {
+                continue;
+            }
+            if let Some(puzzle) = find_puzzle(&board, depth, engine.as_mut(), multi.as_mut()) {
+                println!("{}: {}", puzzle.title, line_text(&puzzle.start, &puzzle.solution));
+                found.push(puzzle);
+            }
+        }
+    }
+
+    if !found.is_empty() {
+        if let Some(dir) = out.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&out).map_err(|e| format!("could not open {}: {}", out.display(), e))?;
+        for puzzle in &found {
+            writeln!(file, "{}", puzzle.to_line()).map_err(|e| format!("could not write {}: {}", out.display(), e))?;
+        }
+    }
+    println!("{} puzzles found in {} positions, added to {}", found.len(), scanned, out.display());
+    return Ok(());
+}
@@ -0,0 +1,74 @@
+use crate::board::{Board, Move, Player, RuleVariant};
+use crate::clock::Clock;
+
+// A game is the position it started from plus every move played since, so any earlier position can be rebuilt
+#[derive(Clone)]
+pub struct Game {
+    pub start: Board,
+    pub board: Board,
+    pub history: Vec<Move>,
+    pub clock: Option<Clock>
+}
+
+impl Game {
+    pub fn new(rules: RuleVariant) -> Self {
+        return Game::from_position(Board::with_rules(rules));
+    }
+
+    pub fn from_position(board: Board) -> Self {
+        Self {
+            start: board.clone(),
+            board: board,
+            history: Vec::new(),
+            clock: None
+        }
+    }
+
+    // Replays a list of moves from a starting position, rejecting any move that is not legal
+    pub fn replay(start: Board, moves: &[Move]) -> Result<Game, String> {
+        let mut game = Game::from_position(start);
+
+        for (ply, &m) in moves.iter().enumerate() {
+            if game.board.is_game_over() || !game.board.get_all_legal_moves(game.board.get_turn()).contains(&m) {
+                return Err(format!("illegal move {} at ply {}", m.to_notation(), ply + 1));
+            }
+            game.play(m);
+        }
+
+        return Ok(game);
+    }
+
+    // Every position of the game in order, from the start up to the current one
+    pub fn positions(&self) -> Vec<Board> {
+        let mut board = self.start.clone();
+        let mut positions = vec![board.clone()];
+        for &m in &self.history {
+            board.move_piece(m);
+            positions.push(board.clone());
+        }
+        return positions;
+    }
+
+    pub fn play(&mut self, m: Move) {
+        self.board.move_piece(m);
+        self.history.push(m);
+
+        // The clock only changes sides once a multi-jump is complete
+        if let Some(clock) = &mut self.clock {
+            if clock.running() != Some(self.board.get_turn()) {
+                clock.switch(self.board.get_turn());
+            }
+        }
+    }
+
+    // Running out of time loses just like running out of pieces or moves
+    pub fn winner(&self) -> Option<Player> {
+        if let Some(flagged) = self.clock.as_ref().and_then(|clock| clock.flagged()) {
+            return Some(flagged.other());
+        }
+        if self.board.is_game_over() {
+            return Some(self.board.get_winner());
+        }
+        return None;
+    }
+}
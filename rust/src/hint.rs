@@ -0,0 +1,65 @@
+use crate::board::{Board, Move, PieceKind, Player};
+use crate::bot::{winner_of, PvLine};
+
+// How far into the expected line the explanation looks, enough for a trade to come back
+const HINT_PLIES: usize = 6;
+
+// A move suggested to the human, for the position it was found in
+pub struct Hint {
+    pub board: u64,
+    pub m: Move,
+    pub explanation: Option<String>
+}
+
+fn material(board: &Board, player: Player) -> (usize, usize) {
+    let pieces = board.get_pieces(player);
+    let kings = pieces.iter().filter(|&&pos| board.at(pos).is_some_and(|piece| piece.kind == PieceKind::KING)).count();
+    return (pieces.len(), kings);
+}
+
+fn pieces_text(count: usize) -> String {
+    return if count == 1 { "a piece".to_string() } else { format!("{} pieces", count) };
+}
+
+impl Hint {
+    pub fn new(board: &Board, line: &PvLine) -> Self {
+        return Self { board: board.hash(), m: line.pv[0], explanation: explain(board, line) };
+    }
+}
+
+// What the line does for the player to move, told from how the material changes along it
+fn explain(board: &Board, line: &PvLine) -> Option<String> {
+    let player = board.get_turn();
+    match winner_of(line.score) {
+        Some(winner) if winner == player => return Some("forces a win".to_string()),
+        _ => ()
+    }
+
+    let (own, own_kings) = material(board, player);
+    let (theirs, _) = material(board, player.other());
+    let mut after = board.clone();
+    for (i, &m) in line.pv.iter().enumerate() {
+        // A multi-jump is finished even if it runs past the lookahead
+        if i >= HINT_PLIES && !after.is_successive() {
+            break;
+        }
+        after.move_piece(m);
+    }
+    let (own_after, own_kings_after) = material(&after, player);
+    let (theirs_after, _) = material(&after, player.other());
+
+    let (lost, taken) = (own - own_after, theirs - theirs_after);
+    if taken > lost {
+        return Some(format!("wins {}", pieces_text(taken - lost)));
+    }
+    if lost > taken {
+        return Some(format!("gives up {}", pieces_text(lost - taken)));
+    }
+    if taken > 0 {
+        return Some("trades pieces".to_string());
+    }
+    if own_kings_after > own_kings {
+        return Some("makes a king".to_string());
+    }
+    return None;
+}
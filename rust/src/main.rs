@@ -1,150 +1,983 @@
+// The code is written with explicit returns, full field initialisers and uppercase variants
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::upper_case_acronyms)]
+
+mod analyse;
+mod arena;
+mod background;
+mod ballot;
+mod bench;
+mod book;
 mod board;
 mod bot;
+mod clock;
+mod editor;
+mod engine;
+mod eval;
+mod extract;
+mod game;
+mod hint;
+mod mcts;
+mod pdn;
+mod review;
+mod review_screen;
+mod puzzle;
+mod puzzle_screen;
+mod rng;
+mod save;
+#[cfg(feature = "serialize")]
+mod serialize;
+mod settings;
+mod settings_menu;
+mod sound;
+mod tablebase;
+mod theme;
+mod tt;
+mod tune;
+mod zobrist;
 
+use background::BackgroundSearch;
 use board::*;
+use book::Book;
 use bot::*;
+use clock::*;
+use editor::{Editor, EditorAction};
+use engine::{AlphaBeta, Engine};
+use eval::EvalParams;
+use game::*;
+use hint::Hint;
+use mcts::{MctsOptions, MCTS_ITERATIONS_PER_LEVEL};
+use puzzle_screen::PuzzleScreen;
+use review_screen::ReviewScreen;
+use rng::Rng;
+use settings::*;
+use settings_menu::*;
+use theme::*;
 
 use raylib::prelude::*;
-use std::{thread, time};
+use std::{env, process, thread, time};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+const AUTOSAVE_INTERVAL: time::Duration = time::Duration::from_secs(30);
+const PANEL_WIDTH: i32 = 240;
+// How many moves the analysis panel shows
+const ANALYSIS_LINES: usize = 3;
+const HINT_TIME: time::Duration = time::Duration::from_millis(500);
+const EVAL_BAR_DEPTH: i32 = 8;
+const EVAL_BAR_WIDTH: i32 = 10;
+// A pawn ahead fills about three quarters of the bar
+const EVAL_BAR_SCALE: f32 = 2.0;
+
+// Everything the bot chooses its moves with
+struct Bot {
+    // The alpha-beta options the engine was built from
+    options: SearchOptions,
+    engine: Arc<Mutex<Box<dyn Engine>>>,
+    // Always alpha-beta, whatever plays the games, since it is the one that can search several lines
+    analyser: Arc<Mutex<Box<dyn Engine>>>,
+    // In analysis mode the human plays both sides and the analyser keeps searching whatever is on the board
+    analysing: bool,
+    // The analyser looking for a move to suggest to the human, and the last move it suggested
+    hint_search: Option<BackgroundSearch>,
+    hint: Option<Hint>,
+    // Scores each position for the evaluation bar, on a single thread since it shares the cores with the bot
+    evaluator: Arc<Mutex<Box<dyn Engine>>>,
+    eval_search: Option<BackgroundSearch>,
+    // The hash of the position that was scored and its score
+    eval: Option<(u64, f32)>,
+    book: Book,
+    rng: Rng,
+    // The search running in the background, either for the bot's move or pondering on the human's time
+    search: Option<BackgroundSearch>,
+    // The latest report from the search, shown in the panel
+    info: Option<SearchInfo>,
+    log: Option<File>
+}
 
-const PIECE_RADIUS: f32 = 30.0;
+// The engine chosen in the settings, shared with the thread that runs its searches
+fn create_engine(options: &SearchOptions, settings: &Settings) -> Arc<Mutex<Box<dyn Engine>>> {
+    let mcts = MctsOptions {
+        exploration: settings.mcts_exploration,
+        playout: settings.mcts_playout,
+        iterations: settings.difficulty as usize * MCTS_ITERATIONS_PER_LEVEL,
+        eval: options.eval.clone(),
+        ..MctsOptions::default()
+    };
+    return Arc::new(Mutex::new(engine::create(settings.engine, options.clone(), mcts)));
+}
+
+fn create_analyser(options: &SearchOptions) -> Arc<Mutex<Box<dyn Engine>>> {
+    let options = SearchOptions { multi_pv: ANALYSIS_LINES, ..options.clone() };
+    return Arc::new(Mutex::new(Box::new(AlphaBeta::new(options))));
+}
 
-fn mark_tile(d: &mut RaylibDrawHandle, width: &i32, height: &i32, row: i32, col: i32) {
-    let tile_width = width / 8;
-    let tile_height = height / 8;
+fn create_evaluator(options: &SearchOptions) -> Arc<Mutex<Box<dyn Engine>>> {
+    let options = SearchOptions { threads: 1, ..options.clone() };
+    return Arc::new(Mutex::new(Box::new(AlphaBeta::new(options))));
+}
+
+impl Bot {
+    // A search that is still running belongs to the old engine, so it is dropped along with it
+    fn configure(&mut self, settings: &Settings) {
+        self.search = None;
+        self.hint_search = None;
+        self.options.threads = settings.threads;
+        self.engine = create_engine(&self.options, settings);
+        self.analyser = create_analyser(&self.options);
+        self.eval_search = None;
+        self.evaluator = create_evaluator(&self.options);
+    }
+
+    fn toggle_analysis(&mut self) {
+        self.analysing = !self.analysing;
+        self.search = None;
+        self.hint_search = None;
+        self.info = None;
+    }
+
+    // Starts over whenever the position has changed, a search that has finished is left showing its lines
+    fn analyse(&mut self, board: &Board) {
+        if self.search.as_ref().is_some_and(|search| search.board.hash() == board.hash()) || board.is_game_over() {
+            return;
+        }
+        self.info = None;
+        self.search = Some(BackgroundSearch::start(board.clone(), SearchLimits::depth(MAX_SEARCH_DEPTH), self.analyser.clone()));
+    }
+
+    fn new_game(&mut self) {
+        self.search = None;
+        self.hint_search = None;
+        self.hint = None;
+        self.eval_search = None;
+        self.eval = None;
+        self.info = None;
+        self.engine.lock().unwrap_or_else(PoisonError::into_inner).new_game();
+    }
+
+    fn request_hint(&mut self, board: &Board) {
+        let asked = self.hint_search.as_ref().map(|search| search.board.hash()).or(self.hint.as_ref().map(|hint| hint.board));
+        if asked == Some(board.hash()) || board.is_game_over() {
+            return;
+        }
+        self.hint = None;
+        self.hint_search = Some(BackgroundSearch::start(board.clone(), SearchLimits::time(HINT_TIME), self.analyser.clone()));
+    }
+
+    fn poll_hint(&mut self) {
+        if !self.hint_search.as_ref().is_some_and(|search| search.is_finished()) {
+            return;
+        }
+        let search = self.hint_search.take().unwrap();
+        let (board, line) = (search.board.clone(), search.reports().pop().and_then(|info| info.lines.into_iter().next()));
+        let result = search.result();
+        let Some(line) = line.or(result.and_then(|result| Some(PvLine { score: result.score, pv: vec![result.best_move?] }))) else {
+            return;
+        };
+        self.hint = Some(Hint::new(&board, &line));
+    }
+
+    // The last score stays on the bar until the position after it has been scored
+    fn evaluate(&mut self, board: &Board) {
+        let scored = self.eval_search.as_ref().map(|search| search.board.hash()).or(self.eval.map(|(hash, _)| hash));
+        if scored == Some(board.hash()) || board.is_game_over() {
+            return;
+        }
+        self.eval_search = Some(BackgroundSearch::start(board.clone(), SearchLimits::depth(EVAL_BAR_DEPTH), self.evaluator.clone()));
+    }
+
+    fn poll_eval(&mut self) {
+        if !self.eval_search.as_ref().is_some_and(|search| search.is_finished()) {
+            return;
+        }
+        let search = self.eval_search.take().unwrap();
+        let hash = search.board.hash();
+        if let Some(result) = search.result() {
+            self.eval = Some((hash, result.score));
+        }
+    }
+
+    // In analysis mode the bar follows the analyser instead
+    fn eval_score(&self) -> Option<f32> {
+        if self.analysing {
+            return self.info.as_ref().map(|info| info.score());
+        }
+        return self.eval.map(|(_, score)| score);
+    }
+
+    fn poll_reports(&mut self) {
+        let Some(search) = &self.search else { return };
+        for info in search.reports() {
+            if let Some(log) = &mut self.log {
+                if let Err(e) = writeln!(log, "{}", info) {
+                    eprintln!("Could not write the search log: {}", e);
+                    self.log = None;
+                }
+            }
+            self.info = Some(info);
+        }
+    }
+}
+
+struct Animation {
+    m: Move,
+    piece: Piece,
+    progress: f32
+}
+
+#[derive(PartialEq)]
+enum Screen {
+    START,
+    GAME,
+    SETTINGS,
+    REVIEW,
+    EDITOR,
+    PUZZLES
+}
+
+fn piece_radius(settings: &Settings) -> f32 {
+    return (settings.board_size / 8) as f32 * 0.3;
+}
+
+// Maps between board positions and the tiles they are drawn on, which only differ when the board is flipped
+fn to_view(settings: &Settings, pos: (i32, i32)) -> (i32, i32) {
+    if settings.flip_board {
+        return (7 - pos.0, 7 - pos.1);
+    }
+    return pos;
+}
+
+fn mark_tile(d: &mut RaylibDrawHandle, theme: &Theme, settings: &Settings, pos: (i32, i32)) {
+    let tile_width = settings.board_size / 8;
+    let tile_height = settings.board_size / 8;
+    let (row, col) = to_view(settings, pos);
 
     let rect = Rectangle::new(col as f32 * tile_width as f32, row as f32 * tile_height as f32, tile_width as f32, tile_height as f32);
-    d.draw_rectangle_lines_ex(rect, 7.5, Color::LIME);
+    d.draw_rectangle_lines_ex(rect, 7.5, theme.highlight);
 }
 
-fn draw_tiles(d: &mut RaylibDrawHandle, board: &Board, width: &i32, height: &i32) {
-    let tile_width = width / 8;
-    let tile_height = height / 8;
+fn draw_tiles(d: &mut RaylibDrawHandle, board: &Board, theme: &Theme, settings: &Settings, hint: Option<&Hint>) {
+    let tile_width = settings.board_size / 8;
+    let tile_height = settings.board_size / 8;
 
-    // Draw the black and white tiles
+    // Draw the light and dark tiles
     for row in 0..8 {
         for col in 0..8 {
-            let colour = if (row + col) % 2 == 0 { Color::WHITE } else { Color::BLACK };
+            let colour = if (row + col) % 2 == 0 { theme.light_square } else { theme.dark_square };
             d.draw_rectangle(col * tile_width, row * tile_height, tile_width, tile_height, colour);
         }
     }
 
     // Mark the correct tiles
-    for row in 0..8 {
-        for col in 0..8 {
-            if board.is_selected() && board.get_selected() == (row, col) {
-                mark_tile(d, width, height, row, col);
+    if let Some(hint) = hint.filter(|hint| hint.board == board.hash()) {
+        mark_tile(d, theme, settings, hint.m.from);
+        mark_tile(d, theme, settings, hint.m.to);
+    }
+    if board.is_selected() {
+        mark_tile(d, theme, settings, board.get_selected());
 
-                for m in board.get_legal_moves(board.get_selected()) {
-                    mark_tile(d, width, height, m.to.0, m.to.1);
-                }
+        for m in board.get_all_legal_moves(board.get_turn()) {
+            if m.from == board.get_selected() {
+                mark_tile(d, theme, settings, m.to);
             }
         }
     }
 }
 
-fn draw_pieces(d: &mut RaylibDrawHandle, board: &Board, width: &i32, height: &i32) {
-    let tile_width = width / 8;
-    let tile_height = height / 8;
+fn draw_labels(d: &mut RaylibDrawHandle, theme: &Theme, settings: &Settings) {
+    let tile_width = settings.board_size / 8;
+    let tile_height = settings.board_size / 8;
+
+    match settings.labels {
+        Labels::NONE => (),
+        Labels::NUMBERS => {
+            for square in 1..=32 {
+                let (row, col) = to_view(settings, from_square(square));
+                d.draw_text(&square.to_string(), col * tile_width + 4, row * tile_height + 4, 14, theme.label);
+            }
+        },
+        Labels::COORDINATES => {
+            // Files along the bottom edge, ranks along the left edge, with a1 in red's left corner
+            for i in 0..8 {
+                let (row, col) = to_view(settings, (7 - i, i));
+                let file = ((b'a' + i as u8) as char).to_string();
+                d.draw_text(&file, col * tile_width + tile_width - 14, 8 * tile_height - 18, 14, theme.label);
+                d.draw_text(&(i + 1).to_string(), 4, row * tile_height + 4, 14, theme.label);
+            }
+        }
+    }
+}
+
+fn draw_king(d: &mut RaylibDrawHandle, theme: &Theme, radius: f32, x: i32, y: i32) {
+    match &theme.king_glyph {
+        KingGlyph::DOT => d.draw_circle(x, y, radius / 5.0, theme.king),
+        KingGlyph::CROWN => {
+            let (x, y) = (x as f32, y as f32);
+            let w = radius * 0.55;
+            let h = radius * 0.45;
+
+            d.draw_rectangle_rec(Rectangle::new(x - w, y, 2.0 * w, h * 0.5), theme.king);
+            for tip in [-w, 0.0, w] {
+                let left = (x + tip - w / 2.0).max(x - w);
+                let right = (x + tip + w / 2.0).min(x + w);
+                d.draw_triangle(Vector2::new(x + tip, y - h), Vector2::new(left, y), Vector2::new(right, y), theme.king);
+            }
+        },
+        KingGlyph::TEXT(text) => {
+            let size = (radius * 0.9) as i32;
+            let text_width = d.measure_text(text, size);
+            d.draw_text(text, x - text_width / 2, y - size / 2, size, theme.king);
+        }
+    }
+}
+
+fn draw_piece(d: &mut RaylibDrawHandle, piece: Piece, theme: &Theme, radius: f32, x: i32, y: i32) {
+    let colour = match piece.player {
+        Player::RED => theme.red_piece,
+        Player::BLACK => theme.black_piece
+    };
+
+    d.draw_circle(x, y, radius, colour);
+    if let Some(outline) = theme.piece_outline {
+        d.draw_circle_lines(x, y, radius, outline);
+    }
+
+    if piece.kind == PieceKind::KING {
+        draw_king(d, theme, radius, x, y);
+    }
+}
+
+fn draw_pieces(d: &mut RaylibDrawHandle, board: &Board, theme: &Theme, settings: &Settings, animation: &Option<Animation>) {
+    let tile_width = settings.board_size / 8;
+    let tile_height = settings.board_size / 8;
+    let centre = |pos: (i32, i32)| {
+        let (row, col) = to_view(settings, pos);
+        return (tile_width as f32 / 2.0 + (col * tile_width) as f32, tile_height as f32 / 2.0 + (row * tile_height) as f32);
+    };
 
     for row in 0..8 {
         for col in 0..8 {
-            match board.at((row, col)) {
-                Some(piece) => {
-                    let x = tile_width / 2 + col as i32 * tile_width;
-                    let y = tile_height / 2 + row as i32 * tile_height;
-
-                    match (piece.kind, piece.player) {
-                        (PieceKind::PAWN, Player::RED) => d.draw_circle(x, y, PIECE_RADIUS, Color::RED),
-                        (PieceKind::KING, Player::RED) => {
-                            d.draw_circle(x, y, PIECE_RADIUS, Color::RED);
-                            // d.draw_circle_lines(x, y, PIECE_RADIUS, Color::GOLD);
-                            d.draw_circle(x, y, PIECE_RADIUS / 5.0, Color::GOLD);
-                        },
-                        (PieceKind::PAWN, Player::BLACK) => d.draw_circle(x, y, PIECE_RADIUS, Color::GRAY),
-                        (PieceKind::KING, Player::BLACK) => {
-                            d.draw_circle(x, y, PIECE_RADIUS, Color::GRAY);
-                            // d.draw_circle_lines(x, y, PIECE_RADIUS, Color::GOLD);
-                            d.draw_circle(x, y, PIECE_RADIUS / 5.0, Color::GOLD);
-                        }
-                    }
-                },
-                None => ()
+            if let Some(piece) = board.at((row, col)) {
+                // The piece that is still sliding is drawn separately below
+                if animation.as_ref().is_some_and(|a| a.m.to == (row, col)) {
+                    continue;
+                }
+
+                let (x, y) = centre((row, col));
+                draw_piece(d, piece, theme, piece_radius(settings), x as i32, y as i32);
             }
         }
     }
+
+    if let Some(a) = animation {
+        let (from, to) = (centre(a.m.from), centre(a.m.to));
+        let x = from.0 + (to.0 - from.0) * a.progress;
+        let y = from.1 + (to.1 - from.1) * a.progress;
+        draw_piece(d, a.piece, theme, piece_radius(settings), x as i32, y as i32);
+    }
 }
 
-fn display_winner(d: &mut RaylibDrawHandle, player: Player, width: &i32, height: &i32) {
-    d.clear_background(Color::WHITE);
+fn display_winner(d: &mut RaylibDrawHandle, theme: &Theme, settings: &Settings, player: Player) {
+    let size = settings.board_size;
+    d.clear_background(theme.background);
     match player {
-        Player::RED => d.draw_text("Red wins!", width / 2 - 240, height / 2 - 50, 100, Color::LIME),
-        Player::BLACK => d.draw_text("Black wins!", width / 2 - 240, height / 2 - 50, 100, Color::LIME)
+        Player::RED => d.draw_text("Red wins!", size / 2 - 240, size / 2 - 50, 100, theme.highlight),
+        Player::BLACK => d.draw_text("Black wins!", size / 2 - 240, size / 2 - 50, 100, theme.highlight)
+    }
+}
+
+fn draw_clock(d: &mut RaylibDrawHandle, clock: &Clock, player: Player, theme: &Theme, x: i32, y: i32) {
+    let name = if player == Player::RED { "Red" } else { "Black" };
+    let remaining = clock.remaining(player);
+
+    if clock.running() == Some(player) {
+        d.draw_rectangle(x - 10, y - 10, PANEL_WIDTH - 20, 90, theme.highlight);
+    }
+    d.draw_text(name, x, y, 20, theme.dark_square);
+    d.draw_text(&format_time(remaining), x, y + 25, 50, if remaining < 10.0 { theme.low_time } else { theme.dark_square });
+}
+
+fn pv_rows(pv: &[Move]) -> Vec<String> {
+    return pv.chunks(3).map(|moves| moves.iter().map(|m| m.to_notation()).collect::<Vec<String>>().join(" ")).collect();
+}
+
+// What the bot last reported, between the clocks when there are any
+fn draw_search_info(d: &mut RaylibDrawHandle, info: &SearchInfo, theme: &Theme, x: i32, top: i32, bottom: i32) {
+    let mut lines = vec![
+        format!("Depth {}", info.depth),
+        format!("Nodes {}", info.nodes),
+        format!("{} nodes/s", info.nps()),
+        format!("Time {:.1}s", info.elapsed.as_secs_f32())
+    ];
+    if info.lines.len() > 1 {
+        // Each line is shortened so the later ones still fit
+        lines.insert(0, "Analysis".to_string());
+        for (i, line) in info.lines.iter().enumerate() {
+            lines.push(format!("{}. {}", i + 1, score_text(line.score)));
+            lines.extend(pv_rows(&line.pv[..line.pv.len().min(6)]));
+        }
+    } else {
+        lines.insert(1, format!("Score {}", score_text(info.score())));
+        lines.push("Best line:".to_string());
+        lines.extend(info.lines.first().map_or(Vec::new(), |line| pv_rows(&line.pv)));
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        let y = top + i as i32 * 22;
+        if y + 20 > bottom {
+            break;
+        }
+        d.draw_text(line, x, y, 20, theme.dark_square);
+    }
+}
+
+// At the bottom of the panel, above the clock when there is one
+fn hint_button(game: &Game, settings: &Settings) -> Rectangle {
+    let bottom = if game.clock.is_some() { settings.board_size - 110 } else { settings.board_size - 20 };
+    return Rectangle::new((settings.board_size + 20) as f32, (bottom - 40) as f32, (PANEL_WIDTH - 40) as f32, 40.0);
+}
+
+fn draw_hint(d: &mut RaylibDrawHandle, game: &Game, theme: &Theme, settings: &Settings, hint: Option<&Hint>) {
+    let rect = hint_button(game, settings);
+    d.draw_rectangle_rec(rect, theme.dark_square);
+    d.draw_text("Hint (H)", rect.x as i32 + 10, rect.y as i32 + 10, 20, theme.light_square);
+
+    if let Some(hint) = hint.filter(|hint| hint.board == game.board.hash()) {
+        d.draw_text(&format!("Try {}", hint.m.to_notation()), rect.x as i32, rect.y as i32 - 54, 20, theme.dark_square);
+        if let Some(explanation) = &hint.explanation {
+            d.draw_text(explanation, rect.x as i32, rect.y as i32 - 30, 20, theme.dark_square);
+        }
+    }
+}
+
+// Red's share of the bar grows from red's side of the board as red gets ahead
+fn draw_eval_bar(d: &mut RaylibDrawHandle, score: f32, theme: &Theme, settings: &Settings) {
+    let (x, top, height) = (settings.board_size + 5, 20, settings.board_size - 40);
+    let red = ((0.5 + 0.5 * (score / EVAL_BAR_SCALE).tanh()) * height as f32).round() as i32;
+    d.draw_rectangle(x, top, EVAL_BAR_WIDTH, height, theme.black_piece);
+    if settings.flip_board {
+        d.draw_rectangle(x, top, EVAL_BAR_WIDTH, red, theme.red_piece);
+    } else {
+        d.draw_rectangle(x, top + height - red, EVAL_BAR_WIDTH, red, theme.red_piece);
+    }
+    d.draw_line(x, top + height / 2, x + EVAL_BAR_WIDTH, top + height / 2, theme.highlight);
+}
+
+fn draw_panel(d: &mut RaylibDrawHandle, game: &Game, theme: &Theme, settings: &Settings, bot: &Bot) {
+    let x = settings.board_size;
+    d.draw_rectangle(x, 0, PANEL_WIDTH, settings.board_size, theme.light_square);
+
+    // Each clock sits on the side of the board its player starts on
+    let mut info_area = (20, settings.board_size - 20);
+    if let Some(clock) = &game.clock {
+        let top = if settings.flip_board { Player::RED } else { Player::BLACK };
+        draw_clock(d, clock, top, theme, x + 20, 20);
+        draw_clock(d, clock, top.other(), theme, x + 20, settings.board_size - 100);
+        d.draw_text(&clock.control.describe(), x + 20, settings.board_size / 2 - 10, 20, theme.dark_square);
+        info_area = (settings.board_size / 2 + 30, settings.board_size - 110);
+    }
+
+    // Hints are for the human's own moves, in analysis mode the panel already shows the best ones
+    if !bot.analysing {
+        draw_hint(d, game, theme, settings, bot.hint.as_ref());
+        info_area.1 -= 100;
+    }
+
+    if let Some(info) = &bot.info {
+        draw_search_info(d, info, theme, x + 20, info_area.0, info_area.1);
+    }
+
+    // Last, so the highlight of the running clock does not cover it
+    if let (true, Some(score)) = (settings.eval_bar, bot.eval_score()) {
+        draw_eval_bar(d, score, theme, settings);
     }
 }
 
-fn draw(mut d: RaylibDrawHandle, board: &Board, width: &i32, height: &i32, winner: Option<Player>) {
-    draw_tiles(&mut d, board, width, height);
-    draw_pieces(&mut d, board, width, height);
+fn draw(mut d: RaylibDrawHandle, game: &Game, theme: &Theme, settings: &Settings, animation: &Option<Animation>, winner: Option<Player>, bot: &Bot) {
+    let board = &game.board;
+    draw_tiles(&mut d, board, theme, settings, bot.hint.as_ref());
+    draw_labels(&mut d, theme, settings);
+    draw_pieces(&mut d, board, theme, settings, animation);
+    draw_panel(&mut d, game, theme, settings, bot);
 
-    let rect = Rectangle::new(0.0, 0.0, *width as f32, *height as f32);
-    let colour = if board.player_turn == Player::RED { Color::RED } else { Color::BLACK };
+    let rect = Rectangle::new(0.0, 0.0, settings.board_size as f32, settings.board_size as f32);
+    let colour = if board.player_turn == Player::RED { theme.red_piece } else { theme.black_piece };
     d.draw_rectangle_lines_ex(rect, 3.0, colour);
 
     if let Some(player) = winner {
-        display_winner(&mut d, player, width, height);
+        display_winner(&mut d, theme, settings, player);
+    }
+}
+
+fn play_move(game: &mut Game, m: Move, settings: &Settings, animation: &mut Option<Animation>, sound: &Option<Sound>) {
+    let piece = game.board.at(m.from).unwrap();
+    game.play(m);
+
+    if settings.animation_speed > 0.0 {
+        *animation = Some(Animation { m: m, piece: piece, progress: 0.0 });
+    }
+    if let (true, Some(sound)) = (settings.sound, sound) {
+        sound.play();
     }
 }
 
-fn update(rl: &mut RaylibHandle, board: &mut Board, mouse: &Vector2) -> Option<Player> {
-    let (row, col) = ((mouse.y / 100.0).floor() as i32, (mouse.x / 100.0).floor() as i32);
+fn update(rl: &mut RaylibHandle, game: &mut Game, settings: &Settings, bot: &mut Bot, animation: &mut Option<Animation>, sound: &Option<Sound>) -> Option<Player> {
+    bot.poll_reports();
+    bot.poll_hint();
+    bot.poll_eval();
+    if settings.eval_bar && !bot.analysing {
+        bot.evaluate(&game.board);
+    }
+
+    // Let the last move finish sliding before anyone moves again
+    if let Some(a) = animation {
+        let distance = ((a.m.to.0 - a.m.from.0).abs()) as f32;
+        a.progress += rl.get_frame_time() * settings.animation_speed / distance;
+        if a.progress < 1.0 {
+            return None;
+        }
+        *animation = None;
+    }
+
+    // The move that just finished sliding may have ended the game, and then nobody is to move
+    if let Some(winner) = game.winner() {
+        return Some(winner);
+    }
+
+    let tile_size = (settings.board_size / 8) as f32;
+    let mouse: Vector2 = rl.get_mouse_position();
+    let (row, col) = to_view(settings, ((mouse.y / tile_size).floor() as i32, (mouse.x / tile_size).floor() as i32));
+
+    let board = &mut game.board;
+    let human_to_move = bot.analysing || board.get_turn() != settings.bot();
+    if bot.analysing {
+        bot.analyse(board);
+    } else if board.get_turn() == settings.bot() {
+        // On the clock the bot thinks for as long as its remaining time allows instead of to a fixed depth
+        let limits = match &game.clock {
+            Some(clock) => SearchLimits::time(clock.budget(settings.bot())),
+            None => SearchLimits::depth(settings.difficulty)
+        };
 
-    if board.get_turn() == Player::BLACK {
-        let m = get_bot_move(&board);
-        board.move_piece(m);
-    } else if rl.is_mouse_button_pressed(raylib::consts::MouseButton::MOUSE_BUTTON_LEFT) {
+        // A search of another position, such as a ponder on a reply the human did not play, is thrown away
+        if bot.search.as_ref().is_some_and(|search| search.board.hash() != board.hash()) {
+            bot.search = None;
+        }
+        match &mut bot.search {
+            Some(search) if search.pondering => search.hit(limits),
+            Some(_) => (),
+            None => {
+                let book_move = if settings.opening_book { bot.book.pick(board, &mut bot.rng) } else { None };
+                match book_move {
+                    Some(m) => play_move(game, m, settings, animation, sound),
+                    None => bot.search = Some(BackgroundSearch::start(board.clone(), limits, bot.engine.clone()))
+                }
+            }
+        }
+
+        if bot.search.as_ref().is_some_and(|search| search.is_finished()) {
+            bot.poll_reports();
+            // A search that failed is simply started again on the next frame
+            let result = bot.search.take().unwrap().result()?;
+            let Some(m) = result.best_move else {
+                return game.winner();
+            };
+            play_move(game, m, settings, animation, sound);
+
+            // Keep thinking on the position after the reply the search expects
+            if let (true, Some(reply)) = (settings.ponder && game.board.get_turn() == settings.human, result.ponder_move) {
+                let mut next = game.board.clone();
+                next.move_piece(reply);
+                if !next.is_game_over() {
+                    bot.search = Some(BackgroundSearch::ponder(next, bot.engine.clone()));
+                }
+            }
+        }
+    }
+
+    let clicked = rl.is_mouse_button_pressed(raylib::consts::MouseButton::MOUSE_BUTTON_LEFT);
+    let hint_pressed = rl.is_key_pressed(KeyboardKey::KEY_H) || (clicked && hint_button(game, settings).check_collision_point_rec(mouse));
+    if !bot.analysing && game.board.get_turn() == settings.human && hint_pressed {
+        bot.request_hint(&game.board);
+    }
+
+    let board = &mut game.board;
+    if human_to_move && clicked {
         if board.at((row, col)).is_some() && board.at((row, col)).unwrap().player == board.get_turn() {
             board.select((row, col));
         } else if board.is_selected() && board.at(board.get_selected()).unwrap().player == board.get_turn() {
             let m = Move::new(board.get_selected(), (row, col));
-            if board.get_legal_moves(board.get_selected()).contains(&m) {
+            if board.get_all_legal_moves(board.get_turn()).contains(&m) {
                 board.deselect();
-                board.move_piece(m);
+                play_move(game, m, settings, animation, sound);
             }
         }
     }
 
-    if board.is_game_over() {
-        let winner = board.get_winner();
-        board.reset();
-        return Some(winner);
+    if animation.is_none() {
+        return game.winner();
     }
 
     return None;
 }
 
+// A fresh game from the standard position or a random ballot, with a clock that has not started
+fn new_game(settings: &Settings, rng: &mut Rng) -> Game {
+    let mut game = Game::new(settings.rules);
+    if let Some(opening) = settings.ballot.random(rng) {
+        if let Err(e) = ballot::play_ballot(&mut game, opening) {
+            eprintln!("Starting from the standard position: {}", e);
+            game = Game::new(settings.rules);
+        }
+    }
+    game.clock = settings.time_control.map(Clock::new);
+    return game;
+}
+
+fn start_button(settings: &Settings, index: i32) -> Rectangle {
+    let size = settings.board_size as f32;
+    return Rectangle::new(size / 2.0 - 150.0, size / 2.0 + index as f32 * 90.0, 300.0, 70.0);
+}
+
+fn draw_start(d: &mut RaylibDrawHandle, theme: &Theme, settings: &Settings, can_continue: bool) {
+    let size = settings.board_size;
+    d.clear_background(theme.light_square);
+    d.draw_text("Checkers", size / 2 - 200, size / 2 - 200, 100, theme.dark_square);
+
+    let options = if can_continue { vec!["Continue (C)", "New game (N)"] } else { vec!["New game (N)"] };
+    for (i, option) in options.iter().enumerate() {
+        let rect = start_button(settings, i as i32);
+        d.draw_rectangle_rec(rect, theme.dark_square);
+        d.draw_text(option, rect.x as i32 + 20, rect.y as i32 + 20, 30, theme.light_square);
+    }
+}
+
+// Returns Some(true) to continue the saved game and Some(false) to start a new one
+fn update_start(rl: &RaylibHandle, settings: &Settings, can_continue: bool) -> Option<bool> {
+    if can_continue && rl.is_key_pressed(KeyboardKey::KEY_C) {
+        return Some(true);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_N) {
+        return Some(false);
+    }
+
+    if rl.is_mouse_button_pressed(raylib::consts::MouseButton::MOUSE_BUTTON_LEFT) {
+        let mouse = rl.get_mouse_position();
+        if can_continue && start_button(settings, 0).check_collision_point_rec(mouse) {
+            return Some(true);
+        }
+        let new_game = if can_continue { 1 } else { 0 };
+        if start_button(settings, new_game).check_collision_point_rec(mouse) {
+            return Some(false);
+        }
+    }
+
+    return None;
+}
+
+fn autosave(game: &Game, settings: &Settings) {
+    // Finished or untouched games are not worth continuing
+    if game.history.is_empty() || game.board.is_game_over() {
+        save::remove();
+    } else if let Err(e) = save::save(game, settings) {
+        eprintln!("Could not save the game: {}", e);
+    }
+}
+
+fn load_personality(path: &Option<String>) -> EvalParams {
+    let Some(path) = path else {
+        return EvalParams::default();
+    };
+    return EvalParams::load(Path::new(path)).unwrap_or_else(|e| {
+        eprintln!("{}, falling back to the built-in weights", e);
+        EvalParams::default()
+    });
+}
+
+fn load_theme(name: &str) -> Theme {
+    return Theme::from_name_or_path(name).unwrap_or_else(|e| {
+        eprintln!("{}, falling back to the classic theme", e);
+        Theme::classic()
+    });
+}
+
+// Command line options override the saved settings for this session
+fn parse_args(settings: &mut Settings) -> Result<(), String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--theme" => {
+                let value = args.next().ok_or("--theme expects a theme name or file")?;
+                Theme::from_name_or_path(&value)?;
+                settings.theme = value;
+            },
+            "--labels" => {
+                let value = args.next().ok_or("--labels expects none, numbers or coordinates")?;
+                settings.labels = Labels::parse(&value).ok_or(format!("unknown label style '{}'", value))?;
+            },
+            "--search-log" => settings.search_log = Some(args.next().ok_or("--search-log expects a file")?),
+            "--eval" => {
+                let value = args.next().ok_or("--eval expects a weights file")?;
+                EvalParams::load(Path::new(&value))?;
+                settings.personality = Some(value);
+            },
+            _ => return Err(format!("unknown argument '{}'", arg))
+        }
+    }
+
+    return Ok(());
+}
+
+// Tools that run in the terminal instead of opening the window, e.g. `checkers bench`
+fn run_tool(args: &[String]) -> Option<Result<(), String>> {
+    return match args.first().map(|arg| arg.as_str()) {
+        Some("analyse") => Some(analyse::run(&args[1..])),
+        Some("arena") => Some(arena::run(&args[1..])),
+        Some("bench") => Some(bench::run(&args[1..])),
+        Some("book") => Some(book::run(&args[1..])),
+        Some("eval") => Some(eval::run(&args[1..])),
+        Some("extract") => Some(extract::run(&args[1..])),
+        Some("review") => Some(review::run(&args[1..])),
+        Some("tablebase") => Some(tablebase::run(&args[1..])),
+        Some("tune") => Some(tune::run(&args[1..])),
+        _ => None
+    };
+}
+
 fn main() {
-    let width: i32 = 800;
-    let height: i32 = 800;
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(result) = run_tool(&args) {
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let mut saved = Settings::load();
+    let mut settings = saved.clone();
+    parse_args(&mut settings).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!("usage: checkers [--theme <{}|file.toml>] [--labels none|numbers|coordinates] [--eval weights.toml] [--search-log file]", BUILTIN_THEMES.join("|"));
+        process::exit(1);
+    });
+    let started = settings.clone();
 
-    let mut board = Board::new();
+    let mut theme = load_theme(&settings.theme);
+    let options = SearchOptions {
+        eval: load_personality(&settings.personality),
+        tablebase: tablebase::load_default().map(Arc::new),
+        threads: settings.threads,
+        ..SearchOptions::default()
+    };
+    let mut bot = Bot {
+        engine: create_engine(&options, &settings),
+        analyser: create_analyser(&options),
+        evaluator: create_evaluator(&options),
+        options: options,
+        book: Book::load_default(),
+        rng: Rng::from_time(),
+        analysing: false,
+        hint_search: None,
+        hint: None,
+        eval_search: None,
+        eval: None,
+        search: None,
+        info: None,
+        log: settings.search_log.as_ref().and_then(|path| {
+            OpenOptions::new().create(true).append(true).open(path).map_err(|e| eprintln!("Could not open the search log {}: {}", path, e)).ok()
+        })
+    };
+    let mut game = new_game(&settings, &mut bot.rng);
+    let mut animation: Option<Animation> = None;
+    let mut menu = SettingsMenu::new();
+    let mut review: Option<ReviewScreen> = None;
+    let mut editor: Option<Editor> = None;
+    let mut puzzles: Option<PuzzleScreen> = None;
+    let can_continue = save::has_autosave();
+    let mut screen = Screen::START;
+    let mut last_save = Instant::now();
+    let mut saved_plies = 0;
 
     let (mut rl, thread) = raylib::init()
-        .size(width, height)
-        .title("Checkers")
+        .size(settings.board_size + PANEL_WIDTH, settings.board_size)
+        .title("Checkers (S: settings, A: analysis, E: editor, P: puzzles)")
         .build();
 
-    let mut d = rl.begin_drawing(&thread);
-    draw(d, &board, &width, &height, None);
+    let audio = RaylibAudio::init_audio_device().ok();
+    let move_sound = audio.as_ref().and_then(sound::move_sound);
+
     while !rl.window_should_close() {
-        let mouse: Vector2 = rl.get_mouse_position();
-        let winner = update(&mut rl, &mut board, &mouse);
+        if screen == Screen::START {
+            match update_start(&rl, &settings, can_continue) {
+                Some(true) => {
+                    match save::load() {
+                        Ok(saved) => {
+                            game = saved.game;
+                            settings.difficulty = saved.difficulty;
+                            settings.human = saved.human;
+                            settings.rules = game.board.rules;
+                            saved_plies = game.history.len();
+                        },
+                        Err(e) => {
+                            eprintln!("Could not restore the saved game: {}", e);
+                            game.clock = settings.time_control.map(Clock::new);
+                        }
+                    }
+                    screen = Screen::GAME;
+                },
+                Some(false) => {
+                    save::remove();
+                    game = new_game(&settings, &mut bot.rng);
+                    bot.new_game();
+                    screen = Screen::GAME;
+                },
+                None => ()
+            }
+
+            let mut d = rl.begin_drawing(&thread);
+            draw_start(&mut d, &theme, &settings, can_continue);
+            continue;
+        }
+
+        if let (Screen::REVIEW, Some(current)) = (&screen, &mut review) {
+            if current.update(&rl, &settings) {
+                review = None;
+                game = new_game(&settings, &mut bot.rng);
+                screen = Screen::GAME;
+            } else {
+                let mut d = rl.begin_drawing(&thread);
+                current.draw(&mut d, &theme, &settings);
+            }
+            continue;
+        }
+
+        if let (Screen::EDITOR, Some(current)) = (&screen, &mut editor) {
+            let action = current.update(&mut rl, &settings);
+            if action.is_none() {
+                let mut d = rl.begin_drawing(&thread);
+                current.draw(&mut d, &theme, &settings);
+                continue;
+            }
+
+            // Either way the game played so far is replaced by one from the new position
+            if let Some(EditorAction::PLAY(board) | EditorAction::ANALYSE(board)) = &action {
+                game = Game::from_position(board.clone());
+                game.clock = settings.time_control.map(Clock::new);
+                saved_plies = 0;
+                bot.new_game();
+                if bot.analysing != matches!(action, Some(EditorAction::ANALYSE(_))) {
+                    bot.toggle_analysis();
+                }
+            }
+            editor = None;
+            screen = Screen::GAME;
+            continue;
+        }
 
-        let mut d = rl.begin_drawing(&thread);
-        draw(d, &board, &width, &height, winner);
+        if let (Screen::PUZZLES, Some(current)) = (&screen, &mut puzzles) {
+            if current.update(&rl, &settings) {
+                puzzles = None;
+                screen = Screen::GAME;
+            } else {
+                let mut d = rl.begin_drawing(&thread);
+                current.draw(&mut d, &theme, &settings);
+            }
+            continue;
+        }
+
+        if screen == Screen::SETTINGS {
+            if menu.update(&rl, &mut settings) {
+                saved = settings.without_overrides(&saved, &started);
+                if let Err(e) = saved.save() {
+                    eprintln!("Could not save settings: {}", e);
+                }
+                theme = load_theme(&settings.theme);
+                bot.configure(&settings);
+                rl.set_window_size(settings.board_size + PANEL_WIDTH, settings.board_size);
+                // New rules and a new time control take effect once the current game is over, unless it has
+                // not started. The moves already played were checked against the rules the game began with.
+                if game.history.is_empty() {
+                    game.start.rules = settings.rules;
+                    game.board.rules = settings.rules;
+                    game.clock = settings.time_control.map(Clock::new);
+                }
+                screen = Screen::GAME;
+            }
+
+            let mut d = rl.begin_drawing(&thread);
+            menu.draw(&mut d, &settings, &theme);
+            continue;
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_S) {
+            game.board.deselect();
+            if let Some(clock) = &mut game.clock {
+                clock.pause();
+            }
+            screen = Screen::SETTINGS;
+            continue;
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_E) {
+            game.board.deselect();
+            if let Some(clock) = &mut game.clock {
+                clock.pause();
+            }
+            editor = Some(Editor::new(&game.board));
+            screen = Screen::EDITOR;
+            continue;
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_P) {
+            game.board.deselect();
+            if let Some(clock) = &mut game.clock {
+                clock.pause();
+            }
+            puzzles = Some(PuzzleScreen::new(bot.options.clone()));
+            screen = Screen::PUZZLES;
+            continue;
+        }
+
+        // Nobody is on the clock while the position is being analysed
+        if rl.is_key_pressed(KeyboardKey::KEY_A) {
+            game.board.deselect();
+            bot.toggle_analysis();
+        }
+        if let Some(clock) = &mut game.clock {
+            match clock.running() {
+                _ if bot.analysing => clock.pause(),
+                None => clock.start(game.board.get_turn()),
+                Some(_) => clock.resume()
+            }
+        }
+
+        let winner = update(&mut rl, &mut game, &settings, &mut bot, &mut animation, &move_sound);
+
+        // The finished game stays on screen for the review, the next one starts when the user leaves it
+        if winner.is_some() {
+            save::remove();
+            saved_plies = 0;
+            bot.new_game();
+            review = Some(ReviewScreen::new(game.clone(), Box::new(AlphaBeta::new(bot.options.clone()))));
+            screen = Screen::REVIEW;
+        } else if game.history.len() != saved_plies && last_save.elapsed() >= AUTOSAVE_INTERVAL {
+            autosave(&game, &settings);
+            saved_plies = game.history.len();
+            last_save = Instant::now();
+        }
+
+        let d = rl.begin_drawing(&thread);
+        draw(d, &game, &theme, &settings, &animation, winner, &bot);
 
         if winner.is_some() {
             thread::sleep(time::Duration::from_millis(5000));
         }
     }
+
+    if screen != Screen::START {
+        autosave(&game, &settings);
+    }
 }
@@ -0,0 +1,225 @@
+use crate::board::{Board, Move, Player};
+use crate::bot::{PvLine, SearchControl, SearchInfo, SearchResult, WIN_SCORE};
+use crate::eval::{evaluate, EvalParams};
+use crate::rng::Rng;
+
+use serde::{Deserialize, Serialize};
+
+pub const MCTS_ITERATIONS: usize = 20_000;
+pub const MCTS_EXPLORATION: f32 = 1.4;
+// In the game every difficulty level is worth this many iterations
+pub const MCTS_ITERATIONS_PER_LEVEL: usize = 3_000;
+
+// Playouts that have not finished by then are scored by the evaluation instead
+const MAX_PLAYOUT_PLIES: usize = 150;
+
+// Only the time is checked every this many iterations, like the node count in the alpha-beta search
+const ITERATIONS_PER_TIME_CHECK: usize = 64;
+const ITERATIONS_PER_INFO: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Playout {
+    // Uniformly random moves
+    RANDOM,
+    // Random, except that a capture is always taken when there is one
+    CAPTURES
+}
+
+#[derive(Clone, Debug)]
+pub struct MctsOptions {
+    // How strongly UCT favours rarely visited moves over the ones that have scored well so far
+    pub exploration: f32,
+    pub playout: Playout,
+    // The budget when there is no time limit
+    pub iterations: usize,
+    // Used to score playouts that are cut off
+    pub eval: EvalParams,
+    // How many of the most visited root moves are reported, each with its own line
+    pub multi_pv: usize
+}
+
+impl Playout {
+    pub fn parse(s: &str) -> Option<Playout> {
+        return match s {
+            "random" => Some(Playout::RANDOM),
+            "captures" => Some(Playout::CAPTURES),
+            _ => None
+        };
+    }
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        Self { exploration: MCTS_EXPLORATION, playout: Playout::CAPTURES, iterations: MCTS_ITERATIONS, eval: EvalParams::default(), multi_pv: 1 }
+    }
+}
+
+struct Node {
+    m: Move,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    // The player who made the move into this node, the rewards are counted for them
+    mover: Player,
+    visits: u32,
+    reward: f32
+}
+
+impl Node {
+    fn new(m: Move, parent: Option<usize>, mover: Player, board: &Board) -> Self {
+        let untried = if board.is_game_over() { Vec::new() } else { board.get_all_legal_moves(board.get_turn()) };
+        Self { m: m, parent: parent, children: Vec::new(), untried: untried, mover: mover, visits: 0, reward: 0.0 }
+    }
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+    options: MctsOptions,
+    rng: Rng
+}
+
+// Red's share of a result, 1 for a red win and 0 for a black one
+fn red_reward(board: &Board, params: &EvalParams) -> f32 {
+    if board.is_game_over() {
+        return if board.get_winner() == Player::RED { 1.0 } else { 0.0 };
+    }
+    return 1.0 / (1.0 + (-evaluate(board, params)).exp());
+}
+
+impl Tree {
+    // UCT: the average reward plus a bonus that shrinks as the move is visited more often
+    fn select_child(&self, node: usize) -> usize {
+        let parent = &self.nodes[node];
+        let log_visits = (parent.visits.max(1) as f32).ln();
+        let uct = |child: usize| {
+            let child = &self.nodes[child];
+            return child.reward / child.visits as f32 + self.options.exploration * (log_visits / child.visits as f32).sqrt();
+        };
+        return *parent.children.iter().max_by(|a, b| uct(**a).total_cmp(&uct(**b))).unwrap();
+    }
+
+    fn expand(&mut self, node: usize, board: &mut Board) -> usize {
+        let index = self.rng.below(self.nodes[node].untried.len());
+        let m = self.nodes[node].untried.swap_remove(index);
+        let mover = board.get_turn();
+        board.move_piece(m);
+
+        let child = self.nodes.len();
+        self.nodes.push(Node::new(m, Some(node), mover, board));
+        self.nodes[node].children.push(child);
+        return child;
+    }
+
+    fn playout(&mut self, board: &mut Board) -> f32 {
+        for _ in 0..MAX_PLAYOUT_PLIES {
+            if board.is_game_over() {
+                break;
+            }
+            let mut moves = board.get_all_legal_moves(board.get_turn());
+            if self.options.playout == Playout::CAPTURES && moves.iter().any(|m| m.is_kill()) {
+                moves.retain(|m| m.is_kill());
+            }
+            board.move_piece(moves[self.rng.below(moves.len())]);
+        }
+        return red_reward(board, &self.options.eval);
+    }
+
+    // One iteration: select down the tree, add a node, play the game out and credit the result back up.
+    // Returns how deep the new node is.
+    fn iterate(&mut self, root: &Board) -> i32 {
+        let mut board = root.clone();
+        let mut node = 0;
+        let mut depth = 0;
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            node = self.select_child(node);
+            board.move_piece(self.nodes[node].m);
+            depth += 1;
+        }
+        if !self.nodes[node].untried.is_empty() {
+            node = self.expand(node, &mut board);
+            depth += 1;
+        }
+
+        let red = self.playout(&mut board);
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.reward += if node.mover == Player::RED { red } else { 1.0 - red };
+            current = node.parent;
+        }
+        return depth;
+    }
+
+    fn most_visited(&self, node: usize) -> Option<usize> {
+        return self.nodes[node].children.iter().copied().max_by_key(|&child| self.nodes[child].visits);
+    }
+
+    // The win rate for red is turned back into the units of the evaluation, the inverse of the sigmoid in red_reward
+    fn score(&self, node: usize) -> f32 {
+        let node = &self.nodes[node];
+        let average = node.reward / node.visits.max(1) as f32;
+        let red = if node.mover == Player::RED { average } else { 1.0 - average };
+        let red = red.clamp(0.0001, 0.9999);
+        return (red / (1.0 - red)).ln().clamp(-WIN_SCORE, WIN_SCORE);
+    }
+
+    // The move into the node followed by the line of most visited moves from there
+    fn principal_variation(&self, node: usize) -> Vec<Move> {
+        let mut pv = vec![self.nodes[node].m];
+        let mut node = node;
+        while let Some(child) = self.most_visited(node) {
+            pv.push(self.nodes[child].m);
+            node = child;
+        }
+        return pv;
+    }
+
+    fn info(&self, depth: i32, iterations: usize, control: &SearchControl) -> SearchInfo {
+        let mut children = self.nodes[0].children.clone();
+        children.sort_by_key(|&child| std::cmp::Reverse(self.nodes[child].visits));
+        children.truncate(self.options.multi_pv.max(1));
+        let lines = children.into_iter().map(|child| PvLine { score: self.score(child), pv: self.principal_variation(child) }).collect();
+        return SearchInfo { depth: depth, nodes: iterations as u64, elapsed: control.elapsed(), lines: lines };
+    }
+}
+
+// Runs until the iterations are used up, the deadline passes or the search is stopped, and plays the
+// move that was visited most often. The depth it reports is how deep the tree has grown.
+pub fn search_with(board: &Board, control: &SearchControl, options: &MctsOptions) -> SearchResult {
+    let mut tree = Tree { nodes: Vec::new(), options: options.clone(), rng: Rng::from_time() };
+    tree.nodes.push(Node::new(Move::default(), None, board.get_turn().other(), board));
+
+    let mut iterations = 0;
+    let mut depth = 0;
+    while !tree.nodes[0].untried.is_empty() || !tree.nodes[0].children.is_empty() {
+        if control.is_stopped() {
+            break;
+        }
+        let check_clock = iterations % ITERATIONS_PER_TIME_CHECK == 0 && iterations > 0;
+        if check_clock && (control.past_deadline() || (!control.has_deadline() && iterations >= options.iterations)) {
+            break;
+        }
+        if iterations % ITERATIONS_PER_INFO == 0 && iterations > 0 {
+            control.report(tree.info(depth, iterations, control));
+        }
+        depth = depth.max(tree.iterate(board));
+        iterations += 1;
+    }
+
+    let mut result = SearchResult { best_move: None, score: 0.0, depth: depth, nodes: iterations as u64, quiescence_nodes: 0, ponder_move: None };
+    let Some(best) = tree.most_visited(0) else {
+        return result;
+    };
+    control.report(tree.info(depth, iterations, control));
+    result.best_move = Some(tree.nodes[best].m);
+    result.score = tree.score(best);
+
+    if let Some(reply) = tree.most_visited(best) {
+        if tree.nodes[reply].mover != tree.nodes[best].mover {
+            result.ponder_move = Some(tree.nodes[reply].m);
+        }
+    }
+    return result;
+}
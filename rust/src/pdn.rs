@@ -0,0 +1,240 @@
+use crate::board::{from_square, to_square, Board, Move, Player, RuleVariant};
+use crate::game::Game;
+
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    WIN(Player),
+    DRAW
+}
+
+impl Outcome {
+    // PDN results are written from the point of view of black, the side that moves first
+    pub fn parse(s: &str) -> Option<Outcome> {
+        return match s {
+            "1-0" | "2-0" => Some(Outcome::WIN(Player::BLACK)),
+            "0-1" | "0-2" => Some(Outcome::WIN(Player::RED)),
+            "1/2-1/2" | "1-1" => Some(Outcome::DRAW),
+            _ => None
+        };
+    }
+
+    pub fn to_pdn(self) -> &'static str {
+        return match self {
+            Outcome::WIN(Player::BLACK) => "1-0",
+            Outcome::WIN(Player::RED) => "0-1",
+            Outcome::DRAW => "1/2-1/2"
+        };
+    }
+
+    // The result as red scores it: 1 for a red win, 0.5 for a draw and 0 for a loss
+    pub fn red_score(&self) -> f32 {
+        return match self {
+            Outcome::WIN(Player::RED) => 1.0,
+            Outcome::WIN(Player::BLACK) => 0.0,
+            Outcome::DRAW => 0.5
+        };
+    }
+}
+
+#[derive(Clone)]
+pub struct PdnGame {
+    pub game: Game,
+    // None for unfinished games, marked "*"
+    pub outcome: Option<Outcome>
+}
+
+fn is_result(token: &str) -> bool {
+    return token == "*" || Outcome::parse(token).is_some();
+}
+
+// Finds the single hops that make up one PDN move. A capture may list every landing square or only the
+// first and last one, so the path between the listed squares is searched for.
+fn find_hops(board: &Board, squares: &[i32]) -> Option<Vec<Move>> {
+    let from = from_square(squares[0]);
+    let target = from_square(squares[1]);
+
+    for m in board.get_all_legal_moves(board.get_turn()).into_iter().filter(|m| m.from == from) {
+        let mut next = board.clone();
+        next.move_piece(m);
+
+        // Once the turn has passed the move has to have ended on the last listed square
+        if !next.is_successive() {
+            if m.to == target && squares.len() == 2 {
+                return Some(vec![m]);
+            }
+            continue;
+        }
+
+        // Still jumping, so carry on from the landing square, crossing off the next square if this hop reached it
+        let mut remaining = vec![to_square(m.to)];
+        remaining.extend_from_slice(if m.to == target { &squares[2..] } else { &squares[1..] });
+        if remaining.len() == 1 {
+            continue;
+        }
+        if let Some(mut hops) = find_hops(&next, &remaining) {
+            hops.insert(0, m);
+            return Some(hops);
+        }
+    }
+
+    return None;
+}
+
+pub fn parse_move(board: &Board, token: &str) -> Result<Vec<Move>, String> {
+    let squares: Result<Vec<i32>, _> = token.split(['-', 'x']).map(|square| square.parse::<i32>()).collect();
+    let squares = squares.map_err(|_| format!("invalid move '{}'", token))?;
+    if squares.len() < 2 || squares.iter().any(|&square| !(1..=32).contains(&square)) {
+        return Err(format!("invalid move '{}'", token));
+    }
+
+    return find_hops(board, &squares).ok_or(format!("illegal move '{}'", token));
+}
+
+// Removes {comments}, (variations) and everything after a % at the start of a line
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut braces = false;
+    let mut parens = 0;
+
+    for line in text.lines() {
+        if line.starts_with('%') {
+            continue;
+        }
+        for c in line.chars() {
+            match c {
+                '{' if !braces => braces = true,
+                '}' if braces => braces = false,
+                '(' if !braces => parens += 1,
+                ')' if !braces && parens > 0 => parens -= 1,
+                _ if braces || parens > 0 => (),
+                _ => out.push(c)
+            }
+        }
+        out.push('\n');
+    }
+
+    return out;
+}
+
+fn finish(number: usize, tags: Vec<(String, String)>, tokens: Vec<String>, outcome: Option<Outcome>) -> Result<PdnGame, String> {
+    let start = match tags.iter().find(|(key, _)| key == "FEN") {
+        Some((_, fen)) => Board::from_fen(fen, RuleVariant::AMERICAN).map_err(|e| format!("game {}: {}", number, e))?,
+        None => Board::with_rules(RuleVariant::AMERICAN)
+    };
+    let mut game = Game::from_position(start);
+
+    for token in tokens {
+        for m in parse_move(&game.board, &token).map_err(|e| format!("game {}: {}", number, e))? {
+            game.play(m);
+        }
+    }
+
+    let outcome = outcome.or(tags.iter().find(|(key, _)| key == "Result").and_then(|(_, result)| Outcome::parse(result)));
+    return Ok(PdnGame { game: game, outcome: outcome });
+}
+
+// Parses every game in a PDN text. Games are played under the American rules, and one that cannot be
+// read is returned as an error without stopping the games after it.
+pub fn parse(text: &str) -> Vec<Result<PdnGame, String>> {
+    let mut games = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let text = strip_comments(text);
+
+    let mut rest = text.as_str();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+
+        // A tag pair such as [Result "1-0"], which starts a new game if moves have already been read
+        if rest.starts_with('[') {
+            let end = rest.find(']').unwrap_or(rest.len() - 1);
+            let pair = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            if !tokens.is_empty() {
+                games.push(finish(games.len() + 1, std::mem::take(&mut tags), std::mem::take(&mut tokens), None));
+            }
+            if let Some((key, value)) = pair.split_once(char::is_whitespace) {
+                tags.push((key.to_string(), value.trim().trim_matches('"').to_string()));
+            }
+            continue;
+        }
+
+        let end = rest.find(|c: char| c.is_whitespace() || c == '[').unwrap_or(rest.len());
+        let token = &rest[..end];
+        rest = &rest[end..];
+
+        if is_result(token) {
+            games.push(finish(games.len() + 1, std::mem::take(&mut tags), std::mem::take(&mut tokens), Outcome::parse(token)));
+            continue;
+        }
+
+        // Move numbers may be glued to the move ("1.11-15"), and annotations like "!" are dropped
+        let token = token.rsplit('.').next().unwrap_or("").trim_end_matches(['!', '?', '*']);
+        if !token.is_empty() {
+            tokens.push(token.to_string());
+        }
+    }
+
+    if !tokens.is_empty() || !tags.is_empty() {
+        games.push(finish(games.len() + 1, tags, tokens, None));
+    }
+
+    return games;
+}
+
+pub fn load(path: &Path) -> Result<Vec<Result<PdnGame, String>>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    return Ok(parse(&text));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squares(moves: &[Move]) -> Vec<(i32, i32)> {
+        return moves.iter().map(|m| (to_square(m.from), to_square(m.to))).collect();
+    }
+
+    #[test]
+    fn compressed_jumps_are_resolved() {
+        // Black's pawn on 9 jumps 14 and then 22
+        let board = Board::from_fen("B:W14,22:B9", RuleVariant::AMERICAN).unwrap();
+        assert_eq!(squares(&parse_move(&board, "9x25").unwrap()), vec![(9, 18), (18, 25)]);
+        assert_eq!(squares(&parse_move(&board, "9x18x25").unwrap()), vec![(9, 18), (18, 25)]);
+        // Stopping halfway is not a whole move
+        assert!(parse_move(&board, "9x18").is_err());
+        assert!(parse_move(&board, "9x27").is_err());
+        assert!(parse_move(&board, "9x33").is_err());
+    }
+
+    #[test]
+    fn comments_and_variations_are_stripped() {
+        let text = "% a line comment\n1. 11-15 {a comment (with a paren} 23-19 (2. 8-11 (2. 9-13 22-18) 22-17) 8-11 {\nover two lines} 22-17";
+        let stripped: Vec<String> = strip_comments(text).split_whitespace().map(|token| token.to_string()).collect();
+        assert_eq!(stripped, vec!["1.", "11-15", "23-19", "8-11", "22-17"]);
+    }
+
+    #[test]
+    fn every_result_token_ends_a_game() {
+        let cases = [
+            ("1-0", Some(Outcome::WIN(Player::BLACK))),
+            ("0-1", Some(Outcome::WIN(Player::RED))),
+            ("1/2-1/2", Some(Outcome::DRAW)),
+            ("*", None)
+        ];
+        for (result, outcome) in cases {
+            let games = parse(&format!("1. 11-15 23-19 {} 1. 9-13 22-18 *", result));
+            assert_eq!(games.len(), 2, "{}", result);
+            let game = games[0].as_ref().unwrap();
+            assert_eq!(game.outcome, outcome, "{}", result);
+            assert_eq!(game.game.history.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use crate::board::{to_square, Board, Move, Player, RuleVariant};
+use crate::pdn::parse_move;
+use crate::settings::data_dir;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BUNDLED_PUZZLES: &str = include_str!("../data/puzzles.txt");
+
+// A position and the line that solves it. The solver plays the first move and every other move of the
+// line is the defence, both sides' hops listed one by one.
+#[derive(Clone)]
+pub struct Puzzle {
+    pub title: String,
+    pub start: Board,
+    pub solution: Vec<Move>
+}
+
+// What solving a puzzle takes, worked out from where its solution ends
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Goal {
+    WIN,
+    // Coming out this many pieces ahead of where the puzzle started
+    MATERIAL(i32)
+}
+
+fn material(board: &Board, player: Player) -> i32 {
+    return board.get_pieces(player).len() as i32;
+}
+
+// How many pieces the mover has won since the start, less the ones given up on the way
+pub fn material_won(start: &Board, board: &Board, mover: Player) -> i32 {
+    let taken = material(start, mover.other()) - material(board, mover.other());
+    let lost = material(start, mover) - material(board, mover);
+    return taken - lost;
+}
+
+pub fn material_balance(board: &Board, player: Player) -> i32 {
+    return material(board, player) - material(board, player.other());
+}
+
+// Nothing can be taken back straight away
+pub fn is_quiet(board: &Board) -> bool {
+    return !board.get_all_legal_moves(board.get_turn()).iter().any(|m| m.is_kill());
+}
+
+// Puzzle lines join the hops of a multi-jump into one move, like PDN
+pub fn line_text(start: &Board, moves: &[Move]) -> String {
+    let mut board = start.clone();
+    let mut tokens: Vec<String> = Vec::new();
+    for &m in moves {
+        if board.is_successive() {
+            tokens.last_mut().unwrap().push_str(&format!("x{}", to_square(m.to)));
+        } else {
+            tokens.push(m.to_notation());
+        }
+        board.move_piece(m);
+    }
+    return tokens.join(" ");
+}
+
+impl Puzzle {
+    // "<fen> | <solution> | <title>", played under the American rules like PDN games
+    pub fn parse(line: &str) -> Result<Puzzle, String> {
+        let fields: Vec<&str> = line.split('|').map(|field| field.trim()).collect();
+        if fields.len() != 3 {
+            return Err(format!("expected 'fen | solution | title' in '{}'", line));
+        }
+
+        let start = Board::from_fen(fields[0], RuleVariant::AMERICAN)?;
+        let mut board = start.clone();
+        let mut solution = Vec::new();
+        for token in fields[1].split_whitespace() {
+            for m in parse_move(&board, token).map_err(|e| format!("puzzle '{}': {}", fields[2], e))? {
+                board.move_piece(m);
+                solution.push(m);
+            }
+        }
+        if solution.is_empty() {
+            return Err(format!("puzzle '{}' has no solution", fields[2]));
+        }
+
+        return Ok(Puzzle { title: fields[2].to_string(), start: start, solution: solution });
+    }
+
+    pub fn solver(&self) -> Player {
+        return self.start.get_turn();
+    }
+
+    pub fn goal(&self) -> Goal {
+        let mut board = self.start.clone();
+        for &m in &self.solution {
+            board.move_piece(m);
+        }
+        if board.is_game_over() && board.get_winner() == self.solver() {
+            return Goal::WIN;
+        }
+        return Goal::MATERIAL(material_won(&self.start, &board, self.solver()));
+    }
+
+    // Whether the solver has got what the puzzle asks for. Material only counts once the other side
+    // has no capture to win it back with.
+    pub fn is_solved(&self, board: &Board) -> bool {
+        if board.is_game_over() {
+            return board.get_winner() == self.solver();
+        }
+        return match self.goal() {
+            Goal::WIN => false,
+            Goal::MATERIAL(pieces) => board.get_turn() != self.solver() && is_quiet(board) && material_won(&self.start, board, self.solver()) >= pieces
+        };
+    }
+
+    pub fn to_line(&self) -> String {
+        return format!("{} | {} | {}", self.start.to_fen(), line_text(&self.start, &self.solution), self.title);
+    }
+}
+
+// Puzzles that cannot be read are reported and left out
+fn parse_pack(name: &str, text: &str) -> Vec<Puzzle> {
+    let mut puzzles = Vec::new();
+    for line in text.lines().map(|line| line.trim()).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        match Puzzle::parse(line) {
+            Ok(puzzle) => puzzles.push(puzzle),
+            Err(e) => eprintln!("Skipping a puzzle in {}: {}", name, e)
+        }
+    }
+    return puzzles;
+}
+
+pub fn packs_dir() -> Option<PathBuf> {
+    return data_dir().map(|dir| dir.join("puzzles"));
+}
+
+pub fn load(path: &Path) -> Result<Vec<Puzzle>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    return Ok(parse_pack(&path.display().to_string(), &text));
+}
+
+// The bundled collection followed by every .txt pack the user has put in the puzzles directory
+pub fn load_all() -> Vec<Puzzle> {
+    let mut puzzles = parse_pack("the bundled puzzles", BUNDLED_PUZZLES);
+    let Some(dir) = packs_dir() else {
+        return puzzles;
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return puzzles;
+    };
+
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|ext| ext == "txt")).collect();
+    paths.sort();
+    for path in paths {
+        match load(&path) {
+            Ok(pack) => puzzles.extend(pack),
+            Err(e) => eprintln!("{}", e)
+        }
+    }
+    return puzzles;
+}
+
+// How the user has done so far, kept between sessions
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PuzzleStats {
+    pub solved: u32,
+    pub failed: u32
+}
+
+impl PuzzleStats {
+    fn path() -> Option<PathBuf> {
+        return data_dir().map(|dir| dir.join("puzzle_stats.toml"));
+    }
+
+    pub fn load() -> PuzzleStats {
+        return PuzzleStats::path().and_then(|path| fs::read_to_string(path).ok()).and_then(|text| toml::from_str(&text).ok()).unwrap_or_default();
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = PuzzleStats::path().ok_or("could not find a data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+        }
+        let text = toml::to_string(self).map_err(|e| e.to_string())?;
+        return fs::write(&path, text).map_err(|e| format!("could not write {}: {}", path.display(), e));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::background::BackgroundSearch;
+use crate::board::{Board, Move, Player, RuleVariant};
+use crate::bot::{winner_of, SearchLimits, SearchOptions};
+use crate::engine::{AlphaBeta, Engine};
+use crate::puzzle::{self, line_text, Puzzle, PuzzleStats};
+use crate::settings::Settings;
+use crate::theme::Theme;
+use crate::{draw_labels, draw_pieces, draw_tiles, mark_tile, to_view, PANEL_WIDTH};
+
+use raylib::prelude::*;
+use std::sync::{Arc, Mutex};
+
+// The defence waits at least this long so the user can see what happened
+const REPLY_DELAY: f64 = 0.6;
+// How deep the engine looks for the defence and when it checks a move that is not the one in the solution
+const PUZZLE_DEPTH: i32 = 8;
+// How much, in pawns, the engine's score may drop below the start position's after a move off the solution
+const KEEP_MARGIN: f32 = 0.5;
+// How many plies longer than the solution the user may take
+const EXTRA_PLIES: usize = 4;
+// Characters per line of text in the panel
+const PANEL_TEXT_WIDTH: usize = 18;
+
+#[derive(PartialEq)]
+enum PuzzleState {
+    SOLVING,
+    SOLVED,
+    // With the reason
+    FAILED(&'static str)
+}
+
+// Plays through the puzzles one at a time, the user looks for a winning move and the engine defends
+pub struct PuzzleScreen {
+    puzzles: Vec<Puzzle>,
+    index: usize,
+    board: Board,
+    // How many plies have been played since the start
+    played: usize,
+    // While both sides have followed the solution the user's moves need no checking
+    scripted: bool,
+    state: PuzzleState,
+    engine: Arc<Mutex<Box<dyn Engine>>>,
+    // The score of the start position for the solver, which a move off the solution has to keep
+    target: Option<f32>,
+    target_search: Option<BackgroundSearch>,
+    // The search checking the user's move and the one choosing the reply
+    check: Option<BackgroundSearch>,
+    defence: Option<BackgroundSearch>,
+    reply_at: f64,
+    last_move: Option<Move>,
+    stats: PuzzleStats
+}
+
+fn wrap(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= PANEL_TEXT_WIDTH => {
+                line.push(' ');
+                line.push_str(word);
+            },
+            _ => lines.push(word.to_string())
+        }
+    }
+    return lines;
+}
+
+impl PuzzleScreen {
+    pub fn new(options: SearchOptions) -> Self {
+        let puzzles = puzzle::load_all();
+        let mut screen = Self {
+            puzzles: puzzles,
+            index: 0,
+            board: Board::with_rules(RuleVariant::AMERICAN),
+            played: 0,
+            scripted: true,
+            state: PuzzleState::SOLVING,
+            engine: Arc::new(Mutex::new(Box::new(AlphaBeta::new(options)))),
+            target: None,
+            target_search: None,
+            check: None,
+            defence: None,
+            reply_at: 0.0,
+            last_move: None,
+            stats: PuzzleStats::load()
+        };
+        screen.start(0);
+        return screen;
+    }
+
+    fn search(&self, board: &Board) -> BackgroundSearch {
+        return BackgroundSearch::start(board.clone(), SearchLimits::depth(PUZZLE_DEPTH), self.engine.clone());
+    }
+
+    fn start(&mut self, index: usize) {
+        if self.puzzles.is_empty() {
+            return;
+        }
+        self.index = index % self.puzzles.len();
+        self.board = self.puzzles[self.index].start.clone();
+        self.played = 0;
+        self.scripted = true;
+        self.state = PuzzleState::SOLVING;
+        self.target = None;
+        self.target_search = Some(self.search(&self.board));
+        self.check = None;
+        self.defence = None;
+        self.last_move = None;
+    }
+
+    fn finish(&mut self, state: PuzzleState) {
+        match state {
+            PuzzleState::SOLVED => self.stats.solved += 1,
+            _ => self.stats.failed += 1
+        }
+        self.state = state;
+        if let Err(e) = self.stats.save() {
+            eprintln!("Could not save the puzzle results: {}", e);
+        }
+    }
+
+    fn play(&mut self, m: Move) {
+        if self.puzzles[self.index].solution.get(self.played) != Some(&m) {
+            self.scripted = false;
+        }
+        self.board.move_piece(m);
+        self.last_move = Some(m);
+        self.played += 1;
+    }
+
+    fn solver_score(&self, score: f32) -> f32 {
+        return if self.puzzles[self.index].solver() == Player::RED { score } else { -score };
+    }
+
+    // After either side has moved: the puzzle is over, or the engine is to find the defence
+    fn advance(&mut self, now: f64) {
+        let puzzle = &self.puzzles[self.index];
+        if puzzle.is_solved(&self.board) {
+            self.finish(PuzzleState::SOLVED);
+        } else if self.board.is_game_over() {
+            self.finish(PuzzleState::FAILED("Not a win"));
+        } else if self.played > puzzle.solution.len() + EXTRA_PLIES {
+            self.finish(PuzzleState::FAILED("Too slow"));
+        } else if self.board.get_turn() != puzzle.solver() {
+            self.defence = Some(self.search(&self.board));
+            self.reply_at = now + REPLY_DELAY;
+        }
+    }
+
+    // A move off the solution is checked once its last hop is played, the rest of a multi-jump is forced
+    fn try_move(&mut self, m: Move, now: f64) {
+        self.play(m);
+        if self.scripted {
+            self.advance(now);
+        } else if self.board.get_turn() != self.puzzles[self.index].solver() || self.board.is_game_over() {
+            self.check = Some(self.search(&self.board));
+        }
+    }
+
+    // A move off the solution is good if the engine finds it still wins, or that it keeps about as much
+    // of an advantage as the start position had
+    fn keeps_the_win(&self, score: f32, target: f32) -> bool {
+        return winner_of(score) == Some(self.puzzles[self.index].solver()) || self.solver_score(score) >= target - KEEP_MARGIN;
+    }
+
+    // Returns true once the user goes back to the game
+    pub fn update(&mut self, rl: &RaylibHandle, settings: &Settings) -> bool {
+        if rl.is_key_pressed(KeyboardKey::KEY_N) {
+            self.start(self.index + 1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_R) {
+            self.start(self.index);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_P) {
+            return true;
+        }
+        if self.puzzles.is_empty() || self.state != PuzzleState::SOLVING {
+            return false;
+        }
+
+        let now = rl.get_time();
+        if self.target_search.as_ref().is_some_and(|search| search.is_finished()) {
+            // Without a score to go by any move is given the benefit of the doubt
+            let score = self.target_search.take().unwrap().result().map(|result| result.score);
+            self.target = Some(score.map_or(f32::NEG_INFINITY, |score| self.solver_score(score)));
+        }
+
+        if self.check.is_some() {
+            if let (true, Some(target)) = (self.check.as_ref().unwrap().is_finished(), self.target) {
+                let result = self.check.take().unwrap().result();
+                if result.is_none_or(|result| self.keeps_the_win(result.score, target)) {
+                    self.advance(now);
+                } else {
+                    self.finish(PuzzleState::FAILED("Wrong move"));
+                }
+            }
+            return false;
+        }
+
+        if self.defence.is_some() {
+            if now >= self.reply_at && self.defence.as_ref().unwrap().is_finished() {
+                match self.defence.take().unwrap().result().and_then(|result| result.best_move) {
+                    Some(m) => {
+                        self.play(m);
+                        self.advance(now);
+                    },
+                    // The search failed, so it is tried again
+                    None => self.defence = Some(self.search(&self.board))
+                }
+            }
+            return false;
+        }
+
+        if rl.is_mouse_button_pressed(raylib::consts::MouseButton::MOUSE_BUTTON_LEFT) {
+            let mouse = rl.get_mouse_position();
+            let tile_size = (settings.board_size / 8) as f32;
+            let pos = to_view(settings, ((mouse.y / tile_size).floor() as i32, (mouse.x / tile_size).floor() as i32));
+            let board = &mut self.board;
+            if board.at(pos).is_some_and(|piece| piece.player == board.get_turn()) {
+                board.select(pos);
+            } else if board.is_selected() {
+                let m = Move::new(board.get_selected(), pos);
+                if board.get_all_legal_moves(board.get_turn()).contains(&m) {
+                    board.deselect();
+                    self.try_move(m, now);
+                }
+            }
+        }
+        return false;
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, theme: &Theme, settings: &Settings) {
+        draw_tiles(d, &self.board, theme, settings, None);
+        if let Some(m) = self.last_move.filter(|_| !self.board.is_selected()) {
+            mark_tile(d, theme, settings, m.from);
+            mark_tile(d, theme, settings, m.to);
+        }
+        draw_labels(d, theme, settings);
+        draw_pieces(d, &self.board, theme, settings, &None);
+
+        let x = settings.board_size + 20;
+        let text = theme.dark_square;
+        d.draw_rectangle(settings.board_size, 0, PANEL_WIDTH, settings.board_size, theme.light_square);
+        d.draw_text("Puzzles", x, 20, 30, text);
+
+        let mut lines: Vec<(String, Color)> = Vec::new();
+        if let Some(puzzle) = self.puzzles.get(self.index) {
+            lines.push((format!("{} of {}", self.index + 1, self.puzzles.len()), text));
+            lines.extend(wrap(&puzzle.title).into_iter().map(|line| (line, text)));
+            lines.push((String::new(), text));
+            match self.state {
+                PuzzleState::SOLVING if self.check.is_some() => lines.push(("Checking...".to_string(), text)),
+                PuzzleState::SOLVING if self.board.get_turn() == puzzle.solver() => lines.push(("Your move".to_string(), text)),
+                PuzzleState::SOLVING => (),
+                PuzzleState::SOLVED => lines.push(("Solved!".to_string(), theme.success)),
+                PuzzleState::FAILED(reason) => {
+                    lines.push((reason.to_string(), theme.error));
+                    lines.push(("The solution:".to_string(), text));
+                    lines.extend(wrap(&line_text(&puzzle.start, &puzzle.solution)).into_iter().map(|line| (line, text)));
+                }
+            }
+        } else {
+            lines.push(("No puzzles found".to_string(), text));
+        }
+
+        lines.push((String::new(), text));
+        lines.push((format!("Solved {}, failed {}", self.stats.solved, self.stats.failed), text));
+        for (i, (line, colour)) in lines.iter().enumerate() {
+            d.draw_text(line, x, 70 + i as i32 * 24, 20, *colour);
+        }
+
+        let side = |player: Player| if player == Player::RED { "red" } else { "black" };
+        let help = [
+            format!("You play {}", self.puzzles.get(self.index).map_or("", |puzzle| side(puzzle.solver()))),
+            "N: next puzzle".to_string(),
+            "R: try again".to_string(),
+            "P: back to the game".to_string()
+        ];
+        for (i, line) in help.iter().enumerate() {
+            d.draw_text(line, x, settings.board_size - 110 + i as i32 * 24, 18, text);
+        }
+    }
+}
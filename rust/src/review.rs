@@ -0,0 +1,154 @@
+use crate::board::{Board, Move, Player};
+use crate::bot::{SearchLimits, SearchOptions, WIN_SCORE};
+use crate::engine::{self, Engine, EngineKind};
+use crate::game::Game;
+use crate::mcts::MctsOptions;
+use crate::pdn;
+use crate::tablebase;
+
+use std::path::Path;
+use std::sync::Arc;
+
+pub const REVIEW_DEPTH: i32 = 8;
+
+// Scores are capped before they are compared, so a win that is still won counts the same however fast it is
+pub const REVIEW_SCORE_CAP: f32 = 8.0;
+
+// How much a move may give away, in pawns, and still count as good, an inaccuracy or a mistake
+const GOOD_DROP: f32 = 0.2;
+const INACCURACY_DROP: f32 = 0.5;
+const MISTAKE_DROP: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Verdict {
+    BEST,
+    GOOD,
+    INACCURACY,
+    MISTAKE,
+    BLUNDER
+}
+
+impl Verdict {
+    fn from_drop(drop: f32) -> Verdict {
+        if drop < GOOD_DROP {
+            return Verdict::GOOD;
+        }
+        if drop < INACCURACY_DROP {
+            return Verdict::INACCURACY;
+        }
+        if drop < MISTAKE_DROP {
+            return Verdict::MISTAKE;
+        }
+        return Verdict::BLUNDER;
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Verdict::BEST => "best",
+            Verdict::GOOD => "good",
+            Verdict::INACCURACY => "inaccuracy",
+            Verdict::MISTAKE => "mistake",
+            Verdict::BLUNDER => "blunder"
+        };
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MoveReview {
+    pub m: Move,
+    pub player: Player,
+    // What the engine would have played instead
+    pub best: Move,
+    // The capped scores of the positions before and after the move, from red's point of view
+    pub before: f32,
+    pub after: f32,
+    // How much the move cost the player who made it
+    pub drop: f32,
+    pub verdict: Verdict
+}
+
+// A finished game is scored as the win it is, anything else is searched
+fn score_position(board: &Board, depth: i32, engine: &mut dyn Engine) -> (f32, Option<Move>) {
+    if board.is_game_over() {
+        let score = if board.get_winner() == Player::RED { WIN_SCORE } else { -WIN_SCORE };
+        return (score.clamp(-REVIEW_SCORE_CAP, REVIEW_SCORE_CAP), None);
+    }
+    engine.set_position(board);
+    let result = engine.search(SearchLimits::depth(depth));
+    return (result.score.clamp(-REVIEW_SCORE_CAP, REVIEW_SCORE_CAP), result.best_move);
+}
+
+// Searches every position of the game and hands over each move's review as soon as the position after
+// it has been scored. Stops early once `report` returns false.
+pub fn review_game(game: &Game, depth: i32, engine: &mut dyn Engine, mut report: impl FnMut(MoveReview) -> bool) {
+    let positions = game.positions();
+    engine.new_game();
+    let (mut before, mut best) = score_position(&positions[0], depth, engine);
+    for (ply, &m) in game.history.iter().enumerate() {
+        let (after, next_best) = score_position(&positions[ply + 1], depth, engine);
+        let player = positions[ply].get_turn();
+        let sign = if player == Player::RED { 1.0 } else { -1.0 };
+        // A shallow search can find the position after the move better than the one before, that is no loss
+        let drop = ((before - after) * sign).max(0.0);
+        let best_move = best.unwrap_or(m);
+        let verdict = if m == best_move { Verdict::BEST } else { Verdict::from_drop(drop) };
+
+        let review = MoveReview { m: m, player: player, best: best_move, before: before, after: after, drop: drop, verdict: verdict };
+        if !report(review) {
+            return;
+        }
+        (before, best) = (after, next_best);
+    }
+}
+
+// `checkers review [--depth d] [--engine alphabeta|mcts|random] <game.pdn>` goes through the first game in the
+// file move by move
+pub fn run(args: &[String]) -> Result<(), String> {
+    let usage = "usage: checkers review [--depth d] [--engine alphabeta|mcts|random] <game.pdn>";
+    let mut depth = REVIEW_DEPTH;
+    let mut kind = EngineKind::ALPHABETA;
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => {
+                let value = args.next().ok_or("--depth expects a number")?;
+                depth = value.parse().ok().filter(|&depth| depth > 0).ok_or(format!("invalid depth '{}'", value))?;
+            },
+            "--engine" => {
+                let value = args.next().ok_or("--engine expects alphabeta, mcts or random")?;
+                kind = EngineKind::parse(value).ok_or(format!("unknown engine '{}'", value))?;
+            },
+            _ => file = Some(arg)
+        }
+    }
+
+    let file = file.ok_or(usage)?;
+    let game = pdn::load(Path::new(file))?.into_iter().next().ok_or(format!("no game in {}", file))??.game;
+    let options = SearchOptions { tablebase: tablebase::load_default().map(Arc::new), ..SearchOptions::default() };
+    let mut engine = engine::create(kind, options, MctsOptions::default());
+
+    let mut counts = [[0; 3]; 2];
+    review_game(&game, depth, engine.as_mut(), |review| {
+        let comment = match review.verdict {
+            Verdict::BEST | Verdict::GOOD => review.verdict.name().to_string(),
+            _ => format!("{} (-{:.2}), best {}", review.verdict.name(), review.drop, review.best.to_notation())
+        };
+        println!("{:>5} {:<6} {:+6.2} {}", if review.player == Player::BLACK { "Black" } else { "Red" }, review.m.to_notation(), review.after, comment);
+
+        let side = if review.player == Player::BLACK { 0 } else { 1 };
+        match review.verdict {
+            Verdict::INACCURACY => counts[side][0] += 1,
+            Verdict::MISTAKE => counts[side][1] += 1,
+            Verdict::BLUNDER => counts[side][2] += 1,
+            _ => ()
+        }
+        return true;
+    });
+
+    for (name, [inaccuracies, mistakes, blunders]) in [("Black", counts[0]), ("Red", counts[1])] {
+        println!("{}: {} inaccuracies, {} mistakes, {} blunders", name, inaccuracies, mistakes, blunders);
+    }
+    return Ok(());
+}
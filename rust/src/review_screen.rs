@@ -0,0 +1,138 @@
+use crate::board::{Board, Player};
+use crate::engine::Engine;
+use crate::game::Game;
+use crate::review::{self, MoveReview, Verdict, REVIEW_DEPTH, REVIEW_SCORE_CAP};
+use crate::settings::Settings;
+use crate::theme::Theme;
+use crate::{draw_labels, draw_pieces, draw_tiles, mark_tile, PANEL_WIDTH};
+
+use raylib::prelude::*;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+// The evaluation graph runs down the panel, one step per ply, with red's advantage to the right
+const GRAPH_TOP: i32 = 150;
+const GRAPH_BOTTOM_MARGIN: i32 = 50;
+
+// Looks back over a finished game while the engine reviews it in the background
+pub struct ReviewScreen {
+    game: Game,
+    positions: Vec<Board>,
+    reviews: Vec<MoveReview>,
+    // Dropping the receiver tells the review thread to stop
+    receiver: Receiver<MoveReview>,
+    // The position on the board, 0 is the start of the game
+    ply: usize
+}
+
+fn verdict_colour(verdict: Verdict, theme: &Theme) -> Option<Color> {
+    return match verdict {
+        Verdict::INACCURACY => Some(theme.inaccuracy),
+        Verdict::MISTAKE => Some(theme.mistake),
+        Verdict::BLUNDER => Some(theme.blunder),
+        _ => None
+    };
+}
+
+impl ReviewScreen {
+    pub fn new(game: Game, mut engine: Box<dyn Engine>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let reviewed = game.clone();
+        thread::spawn(move || review::review_game(&reviewed, REVIEW_DEPTH, engine.as_mut(), |review| sender.send(review).is_ok()));
+        Self { positions: game.positions(), ply: game.history.len(), game: game, reviews: Vec::new(), receiver: receiver }
+    }
+
+    fn graph(&self, settings: &Settings) -> Rectangle {
+        let height = settings.board_size - GRAPH_TOP - GRAPH_BOTTOM_MARGIN;
+        return Rectangle::new((settings.board_size + 20) as f32, GRAPH_TOP as f32, (PANEL_WIDTH - 40) as f32, height as f32);
+    }
+
+    fn point(&self, settings: &Settings, ply: usize, score: f32) -> Vector2 {
+        let graph = self.graph(settings);
+        let x = graph.x + graph.width / 2.0 * (1.0 + score / REVIEW_SCORE_CAP);
+        let y = graph.y + graph.height * ply as f32 / self.game.history.len().max(1) as f32;
+        return Vector2::new(x, y);
+    }
+
+    // Returns true once the user is done and wants a new game
+    pub fn update(&mut self, rl: &RaylibHandle, settings: &Settings) -> bool {
+        self.reviews.extend(self.receiver.try_iter());
+
+        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) || rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.ply = self.ply.saturating_sub(1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) || rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.ply = (self.ply + 1).min(self.game.history.len());
+        }
+
+        // A click on the graph jumps to the ply at that height
+        if rl.is_mouse_button_pressed(raylib::consts::MouseButton::MOUSE_BUTTON_LEFT) {
+            let mouse = rl.get_mouse_position();
+            let graph = self.graph(settings);
+            if graph.check_collision_point_rec(mouse) {
+                let ply = ((mouse.y - graph.y) / graph.height * self.game.history.len() as f32).round() as usize;
+                self.ply = ply.min(self.game.history.len());
+            }
+        }
+
+        return rl.is_key_pressed(KeyboardKey::KEY_N) || rl.is_key_pressed(KeyboardKey::KEY_ENTER);
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, theme: &Theme, settings: &Settings) {
+        let board = &self.positions[self.ply];
+        draw_tiles(d, board, theme, settings, None);
+        draw_labels(d, theme, settings);
+        draw_pieces(d, board, theme, settings, &None);
+
+        let x = settings.board_size + 20;
+        let text = theme.dark_square;
+        d.draw_rectangle(settings.board_size, 0, PANEL_WIDTH, settings.board_size, theme.light_square);
+        d.draw_text("Review", x, 20, 30, text);
+
+        // The move that led here, and what the engine made of it once it has got that far
+        if self.ply > 0 {
+            let m = self.game.history[self.ply - 1];
+            mark_tile(d, theme, settings, m.from);
+            mark_tile(d, theme, settings, m.to);
+            let mover = if self.positions[self.ply - 1].get_turn() == Player::RED { "Red" } else { "Black" };
+            d.draw_text(&format!("{} {}", mover, m.to_notation()), x, 60, 20, text);
+            if let Some(review) = self.reviews.get(self.ply - 1) {
+                d.draw_text(&format!("{} {:+.2}", review.verdict.name(), review.after), x, 85, 20, verdict_colour(review.verdict, theme).unwrap_or(text));
+                if !matches!(review.verdict, Verdict::BEST | Verdict::GOOD) {
+                    d.draw_text(&format!("best was {}", review.best.to_notation()), x, 110, 20, text);
+                }
+            }
+        } else {
+            d.draw_text("Start of the game", x, 60, 20, text);
+        }
+
+        let graph = self.graph(settings);
+        let centre = graph.x + graph.width / 2.0;
+        d.draw_rectangle_lines_ex(graph, 1.0, text);
+        d.draw_line_ex(Vector2::new(centre, graph.y), Vector2::new(centre, graph.y + graph.height), 1.0, text);
+
+        if let Some(first) = self.reviews.first() {
+            let mut previous = self.point(settings, 0, first.before);
+            for (i, review) in self.reviews.iter().enumerate() {
+                let next = self.point(settings, i + 1, review.after);
+                d.draw_line_ex(previous, next, 2.0, theme.red_piece);
+                previous = next;
+            }
+            for (i, review) in self.reviews.iter().enumerate() {
+                if let Some(colour) = verdict_colour(review.verdict, theme) {
+                    d.draw_circle_v(self.point(settings, i + 1, review.after), 5.0, colour);
+                }
+            }
+        }
+
+        let marker = self.point(settings, self.ply, 0.0).y;
+        d.draw_line_ex(Vector2::new(graph.x, marker), Vector2::new(graph.x + graph.width, marker), 2.0, theme.highlight);
+
+        let status = if self.reviews.len() < self.game.history.len() {
+            format!("Reviewing {}/{}", self.reviews.len(), self.game.history.len())
+        } else {
+            "N: new game".to_string()
+        };
+        d.draw_text(&status, x, settings.board_size - 35, 20, text);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::board::{Board, Move, Player, RuleVariant};
+use crate::clock::{Clock, TimeControl};
+use crate::game::Game;
+use crate::settings::{data_dir, player_name, rules_name, Settings};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::PathBuf;
+
+const SAVE_VERSION: u32 = 1;
+
+// Positions are stored as FEN and moves in square notation so the file stays readable and
+// independent of the in-memory layout of Board
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    start: String,
+    moves: Vec<String>,
+    position: String,
+    difficulty: i32,
+    #[serde(with = "player_name")]
+    human: Player,
+    #[serde(with = "rules_name")]
+    rules: RuleVariant,
+    #[serde(default)]
+    clock: Option<SavedClock>
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedClock {
+    control: TimeControl,
+    red: f32,
+    black: f32
+}
+
+pub struct SavedGame {
+    pub game: Game,
+    pub difficulty: i32,
+    pub human: Player
+}
+
+pub fn autosave_path() -> Option<PathBuf> {
+    return data_dir().map(|dir| dir.join("autosave.toml"));
+}
+
+pub fn has_autosave() -> bool {
+    return autosave_path().is_some_and(|path| path.exists());
+}
+
+pub fn save(game: &Game, settings: &Settings) -> Result<(), String> {
+    let path = autosave_path().ok_or("could not find a data directory")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+    }
+
+    let file = SaveFile {
+        version: SAVE_VERSION,
+        start: game.start.to_fen(),
+        moves: game.history.iter().map(|m| m.to_notation()).collect(),
+        position: game.board.to_fen(),
+        difficulty: settings.difficulty,
+        human: settings.human,
+        rules: game.board.rules,
+        clock: game.clock.as_ref().map(|clock| SavedClock {
+            control: clock.control,
+            red: clock.remaining(Player::RED),
+            black: clock.remaining(Player::BLACK)
+        })
+    };
+    let text = toml::to_string_pretty(&file).map_err(|e| e.to_string())?;
+
+    // Write to a temporary file first so a crash mid-write cannot destroy the previous save
+    let temp = path.with_extension("toml.tmp");
+    fs::write(&temp, text).map_err(|e| format!("could not write {}: {}", temp.display(), e))?;
+    return fs::rename(&temp, &path).map_err(|e| format!("could not write {}: {}", path.display(), e));
+}
+
+pub fn load() -> Result<SavedGame, String> {
+    let path = autosave_path().ok_or("could not find a data directory")?;
+    let text = fs::read_to_string(&path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    let file: SaveFile = toml::from_str(&text).map_err(|e| format!("corrupt save file: {}", e.message()))?;
+
+    if file.version != SAVE_VERSION {
+        return Err(format!("unsupported save file version {}", file.version));
+    }
+
+    let start = Board::from_fen(&file.start, file.rules)?;
+    let moves = file.moves.iter().map(|m| Move::from_notation(m)).collect::<Result<Vec<Move>, String>>()?;
+    let mut game = Game::replay(start, &moves)?;
+    game.clock = file.clock.map(|clock| Clock::with_remaining(clock.control, clock.red, clock.black));
+
+    if game.board.to_fen() != file.position {
+        return Err("saved position does not match the saved moves".to_string());
+    }
+
+    return Ok(SavedGame {
+        game: game,
+        difficulty: file.difficulty,
+        human: file.human
+    });
+}
+
+pub fn remove() {
+    if let Some(path) = autosave_path() {
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::board::{from_square, to_square, Board, Piece, PieceKind, Player, RuleVariant};
+
+use serde::{Deserialize, Serialize};
+
+use std::convert::TryFrom;
+
+const BOARD_VERSION: u32 = 1;
+
+// The JSON form of a board. The squares are listed in standard 1-32 order with one character each:
+// '.' for empty, 'b'/'r' for black and red pawns, 'B'/'R' for kings. The selection is UI state and not stored.
+#[derive(Serialize, Deserialize)]
+pub struct BoardRepr {
+    version: u32,
+    turn: Player,
+    rules: RuleVariant,
+    squares: String,
+    successive: Option<i32>
+}
+
+impl From<Board> for BoardRepr {
+    fn from(board: Board) -> Self {
+        let squares = (1..=32).map(|square| match board.at(from_square(square)) {
+            None => '.',
+            Some(piece) => match (piece.player, piece.kind) {
+                (Player::BLACK, PieceKind::PAWN) => 'b',
+                (Player::BLACK, PieceKind::KING) => 'B',
+                (Player::RED, PieceKind::PAWN) => 'r',
+                (Player::RED, PieceKind::KING) => 'R'
+            }
+        }).collect();
+
+        Self {
+            version: BOARD_VERSION,
+            turn: board.player_turn,
+            rules: board.rules,
+            squares: squares,
+            successive: if board.is_successive() { Some(to_square(board.get_successive())) } else { None }
+        }
+    }
+}
+
+impl TryFrom<BoardRepr> for Board {
+    type Error = String;
+
+    fn try_from(repr: BoardRepr) -> Result<Self, Self::Error> {
+        if repr.version != BOARD_VERSION {
+            return Err(format!("unsupported board version {}", repr.version));
+        }
+        if repr.squares.chars().count() != 32 {
+            return Err("a board needs exactly 32 squares".to_string());
+        }
+
+        let mut board = Board::with_rules(repr.rules);
+        board.player_turn = repr.turn;
+        board.pieces = vec![None; 8 * 8];
+
+        for (i, c) in repr.squares.chars().enumerate() {
+            let piece = match c {
+                '.' => None,
+                'b' => Some(Piece::new(PieceKind::PAWN, Player::BLACK)),
+                'B' => Some(Piece::new(PieceKind::KING, Player::BLACK)),
+                'r' => Some(Piece::new(PieceKind::PAWN, Player::RED)),
+                'R' => Some(Piece::new(PieceKind::KING, Player::RED)),
+                _ => return Err(format!("invalid square '{}'", c))
+            };
+            let (row, col) = from_square(i as i32 + 1);
+            board.pieces[row as usize * 8 + col as usize] = piece;
+        }
+
+        if let Some(square) = repr.successive {
+            let pos = from_square(square);
+            if !(1..=32).contains(&square) || board.at(pos).is_none_or(|piece| piece.player != board.player_turn) {
+                return Err(format!("square {} does not hold a piece that can continue jumping", square));
+            }
+            board.set_successive(pos);
+        }
+
+        return Ok(board);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Move;
+
+    fn round_trip(board: &Board) -> Board {
+        let json = serde_json::to_string(board).unwrap();
+        return serde_json::from_str(&json).unwrap();
+    }
+
+    fn repr_json(squares: &str, successive: &str) -> String {
+        return format!("{{\"version\":1,\"turn\":\"black\",\"rules\":\"american\",\"squares\":\"{}\",\"successive\":{}}}", squares, successive);
+    }
+
+    #[test]
+    fn start_position_round_trips() {
+        let board = Board::with_rules(RuleVariant::AMERICAN);
+        let copy = round_trip(&board);
+        assert_eq!(copy.to_fen(), board.to_fen());
+        assert_eq!(copy.rules, RuleVariant::AMERICAN);
+        assert!(!copy.is_successive());
+    }
+
+    #[test]
+    fn multi_jump_in_progress_round_trips() {
+        let mut board = Board::from_fen("B:W14,22:B9", RuleVariant::CASUAL).unwrap();
+        board.move_piece(Move::new(from_square(9), from_square(18)));
+        assert!(board.is_successive());
+
+        let copy = round_trip(&board);
+        assert_eq!(copy.to_fen(), board.to_fen());
+        assert_eq!(copy.get_turn(), Player::BLACK);
+        assert!(copy.is_successive());
+        assert_eq!(copy.get_successive(), from_square(18));
+    }
+
+    #[test]
+    fn board_json_is_compact() {
+        let json = serde_json::to_string(&Board::from_fen("W:WK30:B1", RuleVariant::CASUAL).unwrap()).unwrap();
+        assert_eq!(json, repr_json(&format!("b{}R..", ".".repeat(28)), "null").replace("black", "red").replace("american", "casual"));
+    }
+
+    #[test]
+    fn moves_pieces_and_players_round_trip() {
+        let m = Move::new(from_square(11), from_square(15));
+        assert_eq!(serde_json::from_str::<Move>(&serde_json::to_string(&m).unwrap()).unwrap(), m);
+        let piece = Piece::new(PieceKind::KING, Player::RED);
+        assert_eq!(serde_json::from_str::<Piece>(&serde_json::to_string(&piece).unwrap()).unwrap(), piece);
+        assert_eq!(serde_json::to_string(&Player::BLACK).unwrap(), "\"black\"");
+    }
+
+    #[test]
+    fn rejects_bad_boards() {
+        let empty = ".".repeat(32);
+        assert!(serde_json::from_str::<Board>(&repr_json(&empty, "null")).is_ok());
+
+        // Another version, a missing square, an unknown piece, a jump with no piece to make it and broken JSON
+        assert!(serde_json::from_str::<Board>(&repr_json(&empty, "null").replace("\"version\":1", "\"version\":2")).is_err());
+        assert!(serde_json::from_str::<Board>(&repr_json(&".".repeat(31), "null")).is_err());
+        assert!(serde_json::from_str::<Board>(&repr_json(&format!("x{}", ".".repeat(31)), "null")).is_err());
+        assert!(serde_json::from_str::<Board>(&repr_json(&empty, "9")).is_err());
+        assert!(serde_json::from_str::<Board>(&repr_json(&empty, "33")).is_err());
+        assert!(serde_json::from_str::<Board>("{\"version\":1").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use crate::ballot::BallotDeck;
+use crate::board::{Player, RuleVariant};
+use crate::bot::MINIMAX_DEPTH;
+use crate::clock::TimeControl;
+use crate::engine::EngineKind;
+use crate::mcts::{Playout, MCTS_EXPLORATION};
+use crate::theme::Labels;
+
+use serde::{Deserialize, Serialize};
+
+use std::{env, fs, thread};
+use std::path::{Path, PathBuf};
+
+pub const BOARD_SIZES: [i32; 5] = [560, 640, 720, 800, 880];
+pub const ANIMATION_SPEEDS: [f32; 4] = [0.0, 4.0, 8.0, 16.0];
+pub const MAX_THREADS: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub difficulty: i32,
+    #[serde(with = "player_name")]
+    pub human: Player,
+    pub theme: String,
+    pub labels: Labels,
+    pub board_size: i32,
+    // Squares per second, 0 disables the animation
+    pub animation_speed: f32,
+    pub sound: bool,
+    pub flip_board: bool,
+    // A bar beside the board showing who the engine thinks is winning
+    pub eval_bar: bool,
+    #[serde(with = "rules_name")]
+    pub rules: RuleVariant,
+    // No clocks are used when this is left out
+    pub time_control: Option<TimeControl>,
+    // A TOML or JSON file of evaluation weights for the bot, the built-in weights are used when this is left out
+    pub personality: Option<String>,
+    // Play the first moves from the opening book instead of searching
+    pub opening_book: bool,
+    // Start every game from a random ballot instead of the standard position
+    pub ballot: BallotDeck,
+    // How many threads the bot searches with
+    pub threads: usize,
+    // Let the bot keep searching while the human thinks about their move
+    pub ponder: bool,
+    pub engine: EngineKind,
+    // Only used by the Monte Carlo engine
+    pub mcts_exploration: f32,
+    pub mcts_playout: Playout,
+    // Every report from the bot's searches is appended to this file
+    pub search_log: Option<String>
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: MINIMAX_DEPTH,
+            human: Player::RED,
+            theme: "classic".to_string(),
+            labels: Labels::NONE,
+            board_size: 800,
+            animation_speed: 8.0,
+            sound: true,
+            flip_board: false,
+            eval_bar: true,
+            rules: RuleVariant::CASUAL,
+            time_control: None,
+            personality: None,
+            opening_book: true,
+            ballot: BallotDeck::OFF,
+            threads: available_threads(),
+            ponder: true,
+            engine: EngineKind::ALPHABETA,
+            mcts_exploration: MCTS_EXPLORATION,
+            mcts_playout: Playout::CAPTURES,
+            search_log: None
+        }
+    }
+}
+
+pub fn available_threads() -> usize {
+    return thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_THREADS);
+}
+
+// $XDG_CONFIG_HOME/checkers, falling back to ~/.config/checkers
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("checkers"));
+    }
+    return env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("checkers"));
+}
+
+// $XDG_DATA_HOME/checkers, falling back to ~/.local/share/checkers
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("checkers"));
+    }
+    return env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share").join("checkers"));
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        return config_dir().map(|dir| dir.join("settings.toml"));
+    }
+
+    // Never fails: a missing file gives the defaults, and a corrupt one is moved aside so it can be inspected
+    pub fn load() -> Settings {
+        return match Settings::path() {
+            Some(path) => Settings::load_from(&path),
+            None => Settings::default()
+        };
+    }
+
+    fn load_from(path: &Path) -> Settings {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => return Settings::default()
+        };
+
+        match toml::from_str::<Settings>(&text) {
+            Ok(settings) => return settings.sanitised(),
+            Err(e) => {
+                let backup = path.with_extension("toml.bak");
+                eprintln!("Ignoring corrupt settings file {}: {}", path.display(), e.message());
+                if fs::rename(path, &backup).is_ok() {
+                    eprintln!("The old file was kept as {}", backup.display());
+                }
+                return Settings::default();
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Settings::path().ok_or("could not find a configuration directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+        }
+
+        // Written next to the old file and renamed over it, so a crash never leaves half a file behind
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let temp = path.with_extension("toml.tmp");
+        fs::write(&temp, text).map_err(|e| format!("could not write {}: {}", temp.display(), e))?;
+        return fs::rename(&temp, &path).map_err(|e| format!("could not write {}: {}", path.display(), e));
+    }
+
+    // Command line options only last for the session. Any of them the menu left as they were started
+    // keeps the value from the settings file when the settings are saved.
+    pub fn without_overrides(&self, saved: &Settings, started: &Settings) -> Settings {
+        let mut settings = self.clone();
+        if self.theme == started.theme {
+            settings.theme = saved.theme.clone();
+        }
+        if self.labels == started.labels {
+            settings.labels = saved.labels;
+        }
+        if self.personality == started.personality {
+            settings.personality = saved.personality.clone();
+        }
+        if self.search_log == started.search_log {
+            settings.search_log = saved.search_log.clone();
+        }
+        return settings;
+    }
+
+    // Values edited by hand may be out of range, so clamp them to something playable
+    fn sanitised(mut self) -> Settings {
+        self.difficulty = self.difficulty.clamp(1, 10);
+        self.threads = self.threads.clamp(1, MAX_THREADS);
+        self.board_size = self.board_size.clamp(BOARD_SIZES[0], BOARD_SIZES[BOARD_SIZES.len() - 1]) / 8 * 8;
+        if !self.animation_speed.is_finite() || self.animation_speed < 0.0 {
+            self.animation_speed = 0.0;
+        }
+        if !self.mcts_exploration.is_finite() || self.mcts_exploration < 0.0 {
+            self.mcts_exploration = MCTS_EXPLORATION;
+        }
+        if let Some(control) = &mut self.time_control {
+            control.base = control.base.max(1.0);
+            control.increment = control.increment.max(0.0);
+            control.delay = control.delay.max(0.0);
+        }
+        return self;
+    }
+
+    pub fn bot(&self) -> Player {
+        return self.human.other();
+    }
+}
+
+pub mod player_name {
+    use crate::board::Player;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(player: &Player, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_str(match player {
+            Player::RED => "red",
+            Player::BLACK => "black"
+        });
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Player, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "red" => Ok(Player::RED),
+            "black" => Ok(Player::BLACK),
+            other => Err(de::Error::custom(format!("unknown player '{}'", other)))
+        }
+    }
+}
+
+pub mod rules_name {
+    use crate::board::RuleVariant;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(rules: &RuleVariant, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_str(match rules {
+            RuleVariant::CASUAL => "casual",
+            RuleVariant::AMERICAN => "american"
+        });
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RuleVariant, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "casual" => Ok(RuleVariant::CASUAL),
+            "american" => Ok(RuleVariant::AMERICAN),
+            other => Err(de::Error::custom(format!("unknown rule variant '{}'", other)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A settings file in a directory of its own, so tests running side by side do not share one
+    fn settings_file(name: &str, text: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("checkers-settings-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.toml");
+        fs::write(&path, text).unwrap();
+        return path;
+    }
+
+    #[test]
+    fn a_missing_file_gives_the_defaults() {
+        let path = env::temp_dir().join(format!("checkers-settings-missing-{}", std::process::id())).join("settings.toml");
+        assert_eq!(Settings::load_from(&path), Settings::default());
+    }
+
+    #[test]
+    fn a_corrupt_file_is_moved_aside() {
+        let path = settings_file("corrupt", "difficulty = [\n");
+        assert_eq!(Settings::load_from(&path), Settings::default());
+        assert!(!path.exists());
+        assert!(path.with_extension("toml.bak").exists());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn a_wrong_type_is_treated_as_corrupt() {
+        let path = settings_file("type", "difficulty = \"hard\"\n");
+        assert_eq!(Settings::load_from(&path), Settings::default());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_values_keep_their_defaults() {
+        let path = settings_file("partial", "sound = false\n");
+        assert_eq!(Settings::load_from(&path), Settings { sound: false, ..Settings::default() });
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let text = "difficulty = 99\nthreads = 0\nboard_size = 10000\nanimation_speed = -3.0\nmcts_exploration = nan\n\n[time_control]\nbase = 0.0\nincrement = -1.0\ndelay = -2.0\n";
+        let path = settings_file("range", text);
+        let settings = Settings::load_from(&path);
+        assert_eq!(settings.difficulty, 10);
+        assert_eq!(settings.threads, 1);
+        assert_eq!(settings.board_size, BOARD_SIZES[BOARD_SIZES.len() - 1]);
+        assert_eq!(settings.animation_speed, 0.0);
+        assert_eq!(settings.mcts_exploration, MCTS_EXPLORATION);
+        assert_eq!(settings.time_control, Some(TimeControl { base: 1.0, increment: 0.0, delay: 0.0 }));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let path = settings_file("low", "difficulty = -4\nboard_size = 3\nthreads = 1000\n");
+        let settings = Settings::load_from(&path);
+        assert_eq!(settings.difficulty, 1);
+        assert_eq!(settings.board_size, BOARD_SIZES[0]);
+        assert_eq!(settings.threads, MAX_THREADS);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
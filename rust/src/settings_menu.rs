@@ -0,0 +1,155 @@
+use crate::ballot::BallotDeck;
+use crate::board::{Player, RuleVariant};
+use crate::clock::{TimeControl, TIME_CONTROLS};
+use crate::engine::EngineKind;
+use crate::settings::{available_threads, Settings, ANIMATION_SPEEDS, BOARD_SIZES};
+use crate::theme::{Labels, Theme, BUILTIN_THEMES};
+
+use raylib::prelude::*;
+
+const ITEMS: [&str; 16] = [
+    "Difficulty",
+    "Play as",
+    "Theme",
+    "Labels",
+    "Board size",
+    "Animation speed",
+    "Sound",
+    "Board orientation",
+    "Rules",
+    "Time control",
+    "Opening book",
+    "Opening",
+    "Threads",
+    "Pondering",
+    "Evaluation bar",
+    "Engine"
+];
+
+pub struct SettingsMenu {
+    selected: usize
+}
+
+fn cycle<T: PartialEq + Copy>(options: &[T], current: T, dir: i32) -> T {
+    let index = options.iter().position(|&o| o == current).unwrap_or(0) as i32;
+    let next = (index + dir).rem_euclid(options.len() as i32);
+    return options[next as usize];
+}
+
+fn value_text(settings: &Settings, item: usize) -> String {
+    return match item {
+        0 => settings.difficulty.to_string(),
+        1 => if settings.human == Player::RED { "Red".to_string() } else { "Black".to_string() },
+        2 => settings.theme.clone(),
+        3 => match settings.labels {
+            Labels::NONE => "None".to_string(),
+            Labels::NUMBERS => "Square numbers".to_string(),
+            Labels::COORDINATES => "Coordinates".to_string()
+        },
+        4 => format!("{} px", settings.board_size),
+        5 => if settings.animation_speed == 0.0 { "Off".to_string() } else { format!("{} squares/s", settings.animation_speed) },
+        6 => if settings.sound { "On".to_string() } else { "Off".to_string() },
+        7 => if settings.flip_board { "Flipped".to_string() } else { "Normal".to_string() },
+        8 => match settings.rules {
+            RuleVariant::CASUAL => "Casual".to_string(),
+            RuleVariant::AMERICAN => "American (forced captures)".to_string()
+        },
+        9 => match settings.time_control {
+            None => "Off".to_string(),
+            Some(control) => control.describe()
+        },
+        10 => if settings.opening_book { "On".to_string() } else { "Off".to_string() },
+        11 => match settings.ballot {
+            BallotDeck::OFF => "Standard".to_string(),
+            BallotDeck::TWO => "Random 2-move ballot".to_string(),
+            BallotDeck::THREE => "Random 3-move ballot".to_string()
+        },
+        12 => settings.threads.to_string(),
+        13 => if settings.ponder { "On".to_string() } else { "Off".to_string() },
+        14 => if settings.eval_bar { "On".to_string() } else { "Off".to_string() },
+        _ => match settings.engine {
+            EngineKind::ALPHABETA => "Alpha-beta".to_string(),
+            EngineKind::MCTS => "Monte Carlo tree search".to_string(),
+            EngineKind::RANDOM => "Random mover".to_string()
+        }
+    };
+}
+
+fn change(settings: &mut Settings, item: usize, dir: i32) {
+    match item {
+        0 => settings.difficulty = (settings.difficulty + dir).clamp(1, 10),
+        1 => settings.human = cycle(&[Player::RED, Player::BLACK], settings.human, dir),
+        2 => {
+            // A custom theme file stays selectable until the user cycles away from it
+            let mut themes: Vec<&str> = BUILTIN_THEMES.to_vec();
+            if !themes.contains(&settings.theme.as_str()) {
+                themes.insert(0, &settings.theme);
+            }
+            settings.theme = cycle(&themes, settings.theme.as_str(), dir).to_string();
+        },
+        3 => settings.labels = cycle(&[Labels::NONE, Labels::NUMBERS, Labels::COORDINATES], settings.labels, dir),
+        4 => settings.board_size = cycle(&BOARD_SIZES, settings.board_size, dir),
+        5 => settings.animation_speed = cycle(&ANIMATION_SPEEDS, settings.animation_speed, dir),
+        6 => settings.sound = !settings.sound,
+        7 => settings.flip_board = !settings.flip_board,
+        8 => settings.rules = cycle(&[RuleVariant::CASUAL, RuleVariant::AMERICAN], settings.rules, dir),
+        9 => {
+            let mut controls: Vec<Option<TimeControl>> = vec![None];
+            controls.extend(TIME_CONTROLS.iter().map(|&control| Some(control)));
+            if !controls.contains(&settings.time_control) {
+                controls.insert(1, settings.time_control);
+            }
+            settings.time_control = cycle(&controls, settings.time_control, dir);
+        },
+        10 => settings.opening_book = !settings.opening_book,
+        11 => settings.ballot = cycle(&[BallotDeck::OFF, BallotDeck::TWO, BallotDeck::THREE], settings.ballot, dir),
+        // Up to one thread per core, more only slow the search down
+        12 => settings.threads = (settings.threads as i32 + dir).clamp(1, available_threads().max(settings.threads) as i32) as usize,
+        13 => settings.ponder = !settings.ponder,
+        14 => settings.eval_bar = !settings.eval_bar,
+        _ => settings.engine = cycle(&[EngineKind::ALPHABETA, EngineKind::MCTS, EngineKind::RANDOM], settings.engine, dir)
+    }
+}
+
+impl SettingsMenu {
+    pub fn new() -> Self {
+        Self { selected: 0 }
+    }
+
+    // Returns true once the user leaves the menu
+    pub fn update(&mut self, rl: &RaylibHandle, settings: &mut Settings) -> bool {
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.selected = (self.selected + 1) % ITEMS.len();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+            change(settings, self.selected, 1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
+            change(settings, self.selected, -1);
+        }
+
+        return rl.is_key_pressed(KeyboardKey::KEY_ENTER) || rl.is_key_pressed(KeyboardKey::KEY_S);
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, settings: &Settings, theme: &Theme) {
+        let text = theme.dark_square;
+        d.clear_background(theme.light_square);
+        d.draw_text("Settings", 40, 40, 40, text);
+
+        // Rows shrink to fit the smaller board sizes
+        let spacing = ((settings.board_size - 160) / ITEMS.len() as i32).min(40);
+        for (i, item) in ITEMS.iter().enumerate() {
+            let y = 100 + i as i32 * spacing;
+            if i == self.selected {
+                d.draw_rectangle(30, y - 8, settings.board_size - 60, spacing, theme.highlight);
+            }
+            d.draw_text(item, 40, y, 24, text);
+            d.draw_text(&format!("< {} >", value_text(settings, i)), settings.board_size / 2, y, 24, text);
+        }
+
+        d.draw_text("Up/Down to choose, Left/Right to change, Enter to save", 40, settings.board_size - 50, 20, text);
+    }
+}
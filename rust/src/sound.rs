@@ -0,0 +1,36 @@
+use raylib::prelude::*;
+
+const SAMPLE_RATE: u32 = 22050;
+
+// A short decaying tone played whenever a piece lands, generated here so no asset files need to ship
+fn click_wav() -> Vec<u8> {
+    let samples = SAMPLE_RATE as usize / 20;
+    let mut wav: Vec<u8> = Vec::with_capacity(44 + samples * 2);
+
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + samples as u32 * 2).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(samples as u32 * 2).to_le_bytes());
+
+    for i in 0..samples {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let envelope = (-t * 80.0).exp();
+        let sample = (t * 660.0 * std::f32::consts::TAU).sin() * envelope * 0.4;
+        wav.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+    }
+
+    return wav;
+}
+
+pub fn move_sound(audio: &RaylibAudio) -> Option<Sound<'_>> {
+    let wave = audio.new_wave_from_memory(".wav", &click_wav()).ok()?;
+    return audio.new_sound_from_wave(&wave).ok();
+}
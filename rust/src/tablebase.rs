@@ -0,0 +1,552 @@
+use crate::board::{from_square, Board, Move, Piece, PieceKind, Player, RuleVariant};
+use crate::settings::data_dir;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+pub const TABLEBASE_PIECES: usize = 4;
+
+const MAGIC: &[u8; 4] = b"CKTB";
+const VERSION: u8 = 1;
+
+// Distances are counted in whole turns, a multi-jump is a single turn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TbValue {
+    WIN(i32),
+    LOSS(i32),
+    DRAW
+}
+
+// Piece counts per group, in the order of GROUPS
+type Signature = [usize; 4];
+
+const GROUPS: [(Player, PieceKind); 4] = [
+    (Player::RED, PieceKind::PAWN),
+    (Player::RED, PieceKind::KING),
+    (Player::BLACK, PieceKind::PAWN),
+    (Player::BLACK, PieceKind::KING)
+];
+
+const fn binomials() -> [[u64; 33]; 33] {
+    let mut table = [[0; 33]; 33];
+    let mut n = 0;
+    while n < 33 {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    return table;
+}
+
+const BINOMIAL: [[u64; 33]; 33] = binomials();
+
+// A byte per position: 0 is a draw, 1 to 127 a win in one less turn and 128 to 255 a loss
+fn encode(win: bool, distance: usize) -> u8 {
+    if win {
+        return distance.min(126) as u8 + 1;
+    }
+    return 128 + distance.min(127) as u8;
+}
+
+fn decode(value: u8) -> TbValue {
+    return match value {
+        0 => TbValue::DRAW,
+        1..=127 => TbValue::WIN(value as i32 - 1),
+        _ => TbValue::LOSS(value as i32 - 128)
+    };
+}
+
+fn rules_name(rules: RuleVariant) -> &'static str {
+    return match rules {
+        RuleVariant::CASUAL => "casual",
+        RuleVariant::AMERICAN => "american"
+    };
+}
+
+fn parse_rules(name: &str) -> Result<RuleVariant, String> {
+    return match name {
+        "casual" => Ok(RuleVariant::CASUAL),
+        "american" => Ok(RuleVariant::AMERICAN),
+        _ => Err(format!("unknown rule variant '{}'", name))
+    };
+}
+
+fn group(piece: Piece) -> usize {
+    return GROUPS.iter().position(|&(player, kind)| piece.player == player && piece.kind == kind).unwrap();
+}
+
+fn signature_of(board: &Board) -> Signature {
+    let mut signature = [0; 4];
+    for piece in board.pieces.iter().flatten() {
+        signature[group(*piece)] += 1;
+    }
+    return signature;
+}
+
+fn table_size(signature: Signature) -> usize {
+    let mut free = 32;
+    let mut size = 1;
+    for count in signature {
+        size *= BINOMIAL[free][count] as usize;
+        free -= count;
+    }
+    return size;
+}
+
+// Each group of pieces is ranked with the combinatorial number system among the squares the
+// groups before it left free, so every index up to the table size is a distinct placement
+fn index(board: &Board, signature: Signature) -> usize {
+    let mut occupied = [false; 32];
+    let mut free = 32;
+    let mut index = 0;
+
+    for g in 0..4 {
+        let mut rank = 0;
+        let mut placed = Vec::new();
+        let mut skipped = 0;
+        for square in 0..32 {
+            if occupied[square] {
+                skipped += 1;
+                continue;
+            }
+            if board.at(from_square(square as i32 + 1)).is_some_and(|piece| group(piece) == g) {
+                placed.push(square);
+                rank += BINOMIAL[square - skipped][placed.len()];
+            }
+        }
+
+        index = index * BINOMIAL[free][signature[g]] as usize + rank as usize;
+        free -= signature[g];
+        for square in placed {
+            occupied[square] = true;
+        }
+    }
+
+    return index;
+}
+
+// The inverse of index, None for placements that cannot happen such as a pawn on its own king row
+fn position(signature: Signature, index: usize, turn: Player, rules: RuleVariant) -> Option<Board> {
+    let mut ranks = [0; 4];
+    let mut rest = index;
+    let mut sizes = [0; 4];
+    let mut free = 32;
+    for g in 0..4 {
+        sizes[g] = BINOMIAL[free][signature[g]] as usize;
+        free -= signature[g];
+    }
+    for g in (0..4).rev() {
+        ranks[g] = rest % sizes[g];
+        rest /= sizes[g];
+    }
+
+    let mut board = Board::with_rules(rules);
+    board.pieces = vec![None; 64];
+    board.player_turn = turn;
+    let mut occupied = [false; 32];
+
+    for g in 0..4 {
+        let free_squares: Vec<usize> = (0..32).filter(|&square| !occupied[square]).collect();
+        let mut rank = ranks[g] as u64;
+
+        for i in (1..=signature[g]).rev() {
+            let mut p = free_squares.len() - 1;
+            while BINOMIAL[p][i] > rank {
+                p -= 1;
+            }
+            rank -= BINOMIAL[p][i];
+
+            let square = free_squares[p];
+            let pos = from_square(square as i32 + 1);
+            let (player, kind) = GROUPS[g];
+            if kind == PieceKind::PAWN && pos.0 == if player == Player::RED { 0 } else { 7 } {
+                return None;
+            }
+            occupied[square] = true;
+            board.pieces[pos.0 as usize * 8 + pos.1 as usize] = Some(Piece::new(kind, player));
+        }
+    }
+
+    return Some(board);
+}
+
+// Every position reachable in one whole turn, following multi-jumps to their end
+pub fn successors(board: &Board) -> Vec<Board> {
+    let mut out = Vec::new();
+    for m in board.get_all_legal_moves(board.get_turn()) {
+        let mut next = board.clone();
+        next.move_piece(m);
+        if next.is_successive() {
+            out.extend(successors(&next));
+        } else {
+            out.push(next);
+        }
+    }
+    return out;
+}
+
+// Every position one simple move before this one. Captures and promotions change the pieces on the board,
+// so within one table these are the only moves there are, and undoing one is a step back.
+fn predecessors(board: &Board) -> Vec<Board> {
+    let mover = board.get_turn().other();
+    let mut out = Vec::new();
+    for pos in board.get_pieces(mover) {
+        let piece = board.at(pos).unwrap();
+        let back = if mover == Player::RED { 1 } else { -1 };
+        let rows = if piece.kind == PieceKind::KING { vec![1, -1] } else { vec![back] };
+
+        for row in rows {
+            for col in [1, -1] {
+                let from = (pos.0 + row, pos.1 + col);
+                if !board.in_bounds(from) || !board.is_empty(from) {
+                    continue;
+                }
+                let mut previous = board.clone();
+                previous.set_piece(from, Some(piece));
+                previous.set_piece(pos, None);
+                previous.player_turn = mover;
+                // The step has to have been legal, and with a capture on offer it was not
+                if previous.get_all_legal_moves(mover).contains(&Move::new(from, pos)) {
+                    out.push(previous);
+                }
+            }
+        }
+    }
+    return out;
+}
+
+fn turn_index(turn: Player) -> usize {
+    return match turn {
+        Player::BLACK => 0,
+        Player::RED => 1
+    };
+}
+
+pub struct Tablebase {
+    tables: HashMap<(&'static str, Signature), Vec<u8>>,
+    max_pieces: usize
+}
+
+impl Default for Tablebase {
+    fn default() -> Self {
+        return Tablebase::new();
+    }
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Self { tables: HashMap::new(), max_pieces: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.tables.is_empty();
+    }
+
+    pub fn probe(&self, board: &Board) -> Option<TbValue> {
+        if board.is_successive() {
+            return None;
+        }
+        let signature = signature_of(board);
+
+        // A side without pieces has lost, whoever is to move
+        if signature[0] + signature[1] == 0 || signature[2] + signature[3] == 0 {
+            return Some(if board.get_pieces(board.get_turn()).is_empty() { TbValue::LOSS(0) } else { TbValue::WIN(0) });
+        }
+        if signature.iter().sum::<usize>() > self.max_pieces {
+            return None;
+        }
+
+        let table = self.tables.get(&(rules_name(board.rules), signature))?;
+        let size = table.len() / 2;
+        return Some(decode(table[turn_index(board.get_turn()) * size + index(board, signature)]));
+    }
+
+    fn insert(&mut self, rules: RuleVariant, signature: Signature, table: Vec<u8>) {
+        self.max_pieces = self.max_pieces.max(signature.iter().sum());
+        self.tables.insert((rules_name(rules), signature), table);
+    }
+
+    // $XDG_DATA_HOME/checkers/tablebase
+    pub fn default_dir() -> Option<PathBuf> {
+        return data_dir().map(|dir| dir.join("tablebase"));
+    }
+
+    // Reads every table in the directory, files that are not tables are skipped
+    pub fn load(dir: &Path) -> Result<Tablebase, String> {
+        let mut tablebase = Tablebase::new();
+        let entries = fs::read_dir(dir).map_err(|e| format!("could not read {}: {}", dir.display(), e))?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "tb") {
+                continue;
+            }
+            let data = fs::read(&path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+            if data.len() < 10 || &data[0..4] != MAGIC || data[4] != VERSION {
+                return Err(format!("{} is not a tablebase file of version {}", path.display(), VERSION));
+            }
+
+            let rules = if data[5] == 0 { RuleVariant::CASUAL } else { RuleVariant::AMERICAN };
+            let signature = [data[6] as usize, data[7] as usize, data[8] as usize, data[9] as usize];
+            if data.len() - 10 != 2 * table_size(signature) {
+                return Err(format!("{} is truncated", path.display()));
+            }
+            tablebase.insert(rules, signature, data[10..].to_vec());
+        }
+
+        return Ok(tablebase);
+    }
+
+    fn save(dir: &Path, rules: RuleVariant, signature: Signature, table: &[u8]) -> Result<(), String> {
+        let path = dir.join(format!("{}-{}{}{}{}.tb", rules_name(rules), signature[0], signature[1], signature[2], signature[3]));
+        let mut data = MAGIC.to_vec();
+        data.push(VERSION);
+        data.push(if rules == RuleVariant::CASUAL { 0 } else { 1 });
+        data.extend(signature.iter().map(|&count| count as u8));
+        data.extend_from_slice(table);
+        return fs::write(&path, data).map_err(|e| format!("could not write {}: {}", path.display(), e));
+    }
+
+    // Solves one signature by retrograde analysis. Positions that are won or lost straight away seed the
+    // search, and results then spread back to the parents in order of distance: a parent of a loss is a win
+    // one turn later, and a parent whose every successor is a win for the opponent is a loss one turn after
+    // the longest of them. Parents are found by undoing moves, so no graph of the moves is kept. What is
+    // never reached is a draw.
+    fn solve(&self, rules: RuleVariant, signature: Signature) -> Vec<u8> {
+        let size = table_size(signature);
+        let nodes = 2 * size;
+
+        // Successors not yet known to be won by the opponent, the node is lost once this reaches zero. It
+        // stays zero for positions that are over, which have no successors.
+        let mut remaining = vec![0u32; nodes];
+        let mut longest_win = vec![0usize; nodes];
+        let mut buckets: Vec<Vec<(u32, bool)>> = Vec::new();
+
+        fn push(buckets: &mut Vec<Vec<(u32, bool)>>, distance: usize, node: u32, win: bool) {
+            if buckets.len() <= distance {
+                buckets.resize(distance + 1, Vec::new());
+            }
+            buckets[distance].push((node, win));
+        }
+
+        for node in 0..nodes {
+            let turn = if node < size { Player::BLACK } else { Player::RED };
+            let Some(board) = position(signature, node % size, turn, rules) else { continue };
+
+            if board.is_game_over() {
+                push(&mut buckets, 0, node as u32, board.get_winner() == turn);
+                continue;
+            }
+
+            for child in successors(&board) {
+                if signature_of(&child) == signature {
+                    remaining[node] += 1;
+                    continue;
+                }
+
+                // Captures and promotions lead to tables that were solved earlier, and those results are final
+                match self.probe(&child) {
+                    Some(TbValue::LOSS(distance)) => {
+                        push(&mut buckets, distance as usize + 1, node as u32, true);
+                        remaining[node] += 1;
+                    },
+                    Some(TbValue::WIN(distance)) => longest_win[node] = longest_win[node].max(distance as usize),
+                    _ => remaining[node] += 1
+                }
+            }
+
+            if remaining[node] == 0 {
+                push(&mut buckets, longest_win[node] + 1, node as u32, false);
+            }
+        }
+
+        let mut table = vec![0u8; nodes];
+        let mut distance = 0;
+        while distance < buckets.len() {
+            let bucket = std::mem::take(&mut buckets[distance]);
+            for (node, win) in bucket {
+                if table[node as usize] != 0 {
+                    continue;
+                }
+                table[node as usize] = encode(win, distance);
+
+                let turn = if (node as usize) < size { Player::BLACK } else { Player::RED };
+                let board = position(signature, node as usize % size, turn, rules).unwrap();
+                for parent in predecessors(&board) {
+                    let parent = turn_index(parent.get_turn()) * size + index(&parent, signature);
+                    if table[parent] != 0 || remaining[parent] == 0 {
+                        continue;
+                    }
+                    if !win {
+                        push(&mut buckets, distance + 1, parent as u32, true);
+                    } else {
+                        remaining[parent] -= 1;
+                        longest_win[parent] = longest_win[parent].max(distance);
+                        if remaining[parent] == 0 {
+                            push(&mut buckets, longest_win[parent] + 1, parent as u32, false);
+                        }
+                    }
+                }
+            }
+            distance += 1;
+        }
+
+        return table;
+    }
+
+    // Signatures are solved from fewest pieces up, and with the same number of pieces from fewest pawns
+    // up, so that every capture or promotion leads into a table that is already done
+    pub fn generate(rules: RuleVariant, max_pieces: usize, dir: &Path) -> Result<Tablebase, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+
+        let mut signatures: Vec<Signature> = Vec::new();
+        for red_pawns in 0..=max_pieces {
+            for red_kings in 0..=max_pieces {
+                for black_pawns in 0..=max_pieces {
+                    for black_kings in 0..=max_pieces {
+                        let signature = [red_pawns, red_kings, black_pawns, black_kings];
+                        if signature.iter().sum::<usize>() <= max_pieces && red_pawns + red_kings > 0 && black_pawns + black_kings > 0 {
+                            signatures.push(signature);
+                        }
+                    }
+                }
+            }
+        }
+        signatures.sort_by_key(|s| (s.iter().sum::<usize>(), s[0] + s[2]));
+
+        let mut tablebase = Tablebase::new();
+        for signature in signatures {
+            let start = Instant::now();
+            let table = tablebase.solve(rules, signature);
+            Tablebase::save(dir, rules, signature, &table)?;
+
+            let wins = table.iter().filter(|&&value| matches!(decode(value), TbValue::WIN(_))).count();
+            let losses = table.iter().filter(|&&value| matches!(decode(value), TbValue::LOSS(_))).count();
+            println!("{:?} {:>10} positions, {:>10} wins, {:>10} losses, {:.1}s", signature, table.len(), wins, losses, start.elapsed().as_secs_f32());
+            tablebase.insert(rules, signature, table);
+        }
+
+        return Ok(tablebase);
+    }
+}
+
+// The tables in the data directory, if any have been generated. A broken table is reported and the
+// game goes on without the tablebase.
+pub fn load_default() -> Option<Tablebase> {
+    let dir = Tablebase::default_dir().filter(|dir| dir.is_dir())?;
+    return match Tablebase::load(&dir) {
+        Ok(tablebase) => if tablebase.is_empty() { None } else { Some(tablebase) },
+        Err(e) => {
+            eprintln!("Not using the endgame tablebase: {}", e);
+            None
+        }
+    };
+}
+
+// `checkers tablebase generate [--pieces n] [--rules casual|american] [--dir path]` builds the tables,
+// `checkers tablebase probe <fen> [--rules ...] [--dir path]` looks a position up
+pub fn run(args: &[String]) -> Result<(), String> {
+    let usage = "usage: checkers tablebase generate|probe <fen> [--pieces n] [--rules casual|american] [--dir path]";
+    let mut pieces = TABLEBASE_PIECES;
+    let mut rules = RuleVariant::CASUAL;
+    let mut dir = Tablebase::default_dir();
+    let mut positional: Vec<&str> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pieces" => {
+                let value = args.next().ok_or("--pieces expects a number")?;
+                pieces = value.parse().map_err(|_| format!("invalid number of pieces '{}'", value))?;
+            },
+            "--rules" => rules = parse_rules(args.next().ok_or("--rules expects casual or american")?)?,
+            "--dir" => dir = Some(PathBuf::from(args.next().ok_or("--dir expects a directory")?)),
+            _ => positional.push(arg)
+        }
+    }
+    let dir = dir.ok_or("could not find a data directory, use --dir")?;
+
+    match positional.as_slice() {
+        ["generate"] => {
+            Tablebase::generate(rules, pieces, &dir)?;
+            println!("Tables written to {}", dir.display());
+        },
+        ["probe", fen] => {
+            let tablebase = Tablebase::load(&dir)?;
+            let board = Board::from_fen(fen, rules)?;
+            match tablebase.probe(&board) {
+                Some(TbValue::WIN(distance)) => println!("Win in {} turns", distance),
+                Some(TbValue::LOSS(distance)) => println!("Loss in {} turns", distance),
+                Some(TbValue::DRAW) => println!("Draw"),
+                None => println!("Not in the tablebase")
+            }
+            for child in successors(&board) {
+                if let Some(value) = tablebase.probe(&child) {
+                    println!("  {:<40} {:?}", child.to_fen(), value);
+                }
+            }
+        },
+        _ => return Err(usage.to_string())
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Solves the signatures in the order given, each may capture into the ones before it
+    fn solved(rules: RuleVariant, signatures: &[Signature]) -> Tablebase {
+        let mut tablebase = Tablebase::new();
+        for &signature in signatures {
+            let table = tablebase.solve(rules, signature);
+            tablebase.insert(rules, signature, table);
+        }
+        return tablebase;
+    }
+
+    #[test]
+    fn index_and_position_are_inverse() {
+        for signature in [[0, 2, 0, 1], [1, 1, 1, 0], [1, 0, 2, 0]] {
+            let mut placements = 0;
+            for n in 0..table_size(signature) {
+                let Some(board) = position(signature, n, Player::RED, RuleVariant::CASUAL) else { continue };
+                assert_eq!(signature_of(&board), signature);
+                let i = index(&board, signature);
+                assert_eq!(i, n, "{}", board.to_fen());
+                assert_eq!(position(signature, i, Player::RED, RuleVariant::CASUAL).unwrap().to_fen(), board.to_fen());
+                placements += 1;
+            }
+            assert!(placements > 0);
+        }
+    }
+
+    // Each king is safe in a double corner. A king in a single corner can be shut in, so one king against
+    // another is not a draw everywhere.
+    #[test]
+    fn kings_in_the_double_corners_draw() {
+        let tablebase = solved(RuleVariant::CASUAL, &[[0, 1, 0, 1]]);
+        for fen in ["W:WK32:BK1", "B:WK32:BK1", "W:WK28:BK5", "B:WK28:BK5"] {
+            let board = Board::from_fen(fen, RuleVariant::CASUAL).unwrap();
+            assert_eq!(tablebase.probe(&board), Some(TbValue::DRAW), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn two_kings_beat_one_from_either_side() {
+        let tablebase = solved(RuleVariant::CASUAL, &[[0, 1, 0, 1], [0, 2, 0, 1]]);
+
+        let red_to_move = Board::from_fen("W:WK29,K30:BK4", RuleVariant::CASUAL).unwrap();
+        assert!(matches!(tablebase.probe(&red_to_move), Some(TbValue::WIN(distance)) if distance > 0));
+
+        let black_to_move = Board::from_fen("B:WK29,K30:BK4", RuleVariant::CASUAL).unwrap();
+        assert!(matches!(tablebase.probe(&black_to_move), Some(TbValue::LOSS(distance)) if distance > 0));
+    }
+}
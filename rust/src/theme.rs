@@ -0,0 +1,260 @@
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Labels {
+    NONE,
+    NUMBERS,
+    COORDINATES
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum KingGlyph {
+    DOT,
+    CROWN,
+    TEXT(String)
+}
+
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: String,
+    pub light_square: Color,
+    pub dark_square: Color,
+    pub red_piece: Color,
+    pub black_piece: Color,
+    pub piece_outline: Option<Color>,
+    pub king: Color,
+    pub king_glyph: KingGlyph,
+    pub highlight: Color,
+    pub label: Color,
+    pub background: Color,
+    // A clock running out of time is drawn in this colour
+    pub low_time: Color,
+    // Messages in the side panel about something that went right or wrong
+    pub success: Color,
+    pub error: Color,
+    // Moves the game review marks as weaker than the best one, from the mildest up
+    pub inaccuracy: Color,
+    pub mistake: Color,
+    pub blunder: Color
+}
+
+// Every field is optional so that a theme file only has to list what it changes from its base
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: Option<String>,
+    base: Option<String>,
+    light_square: Option<String>,
+    dark_square: Option<String>,
+    red_piece: Option<String>,
+    black_piece: Option<String>,
+    piece_outline: Option<String>,
+    king: Option<String>,
+    king_glyph: Option<String>,
+    highlight: Option<String>,
+    label: Option<String>,
+    background: Option<String>,
+    low_time: Option<String>,
+    success: Option<String>,
+    error: Option<String>,
+    inaccuracy: Option<String>,
+    mistake: Option<String>,
+    blunder: Option<String>
+}
+
+pub const BUILTIN_THEMES: [&str; 3] = ["classic", "wood", "high-contrast"];
+
+impl Labels {
+    pub fn parse(s: &str) -> Option<Labels> {
+        match s {
+            "none" => Some(Labels::NONE),
+            "numbers" => Some(Labels::NUMBERS),
+            "coordinates" | "coords" => Some(Labels::COORDINATES),
+            _ => None
+        }
+    }
+}
+
+impl KingGlyph {
+    pub fn parse(s: &str) -> KingGlyph {
+        match s {
+            "dot" | "" => KingGlyph::DOT,
+            "crown" => KingGlyph::CROWN,
+            text => KingGlyph::TEXT(text.to_string())
+        }
+    }
+}
+
+impl Theme {
+    pub fn classic() -> Self {
+        Self {
+            name: "classic".to_string(),
+            light_square: Color::WHITE,
+            dark_square: Color::BLACK,
+            red_piece: Color::RED,
+            black_piece: Color::GRAY,
+            piece_outline: None,
+            king: Color::GOLD,
+            king_glyph: KingGlyph::DOT,
+            highlight: Color::LIME,
+            label: Color::GRAY,
+            background: Color::WHITE,
+            low_time: Color::RED,
+            success: Color::new(0, 140, 40, 255),
+            error: Color::RED,
+            inaccuracy: Color::new(200, 150, 0, 255),
+            mistake: Color::new(230, 110, 0, 255),
+            blunder: Color::new(170, 0, 0, 255)
+        }
+    }
+
+    pub fn wood() -> Self {
+        Self {
+            name: "wood".to_string(),
+            light_square: Color::new(240, 217, 181, 255),
+            dark_square: Color::new(140, 90, 55, 255),
+            red_piece: Color::new(178, 34, 34, 255),
+            black_piece: Color::new(30, 30, 30, 255),
+            piece_outline: Some(Color::new(245, 235, 215, 255)),
+            king: Color::GOLD,
+            king_glyph: KingGlyph::CROWN,
+            highlight: Color::new(120, 200, 80, 255),
+            label: Color::new(245, 235, 215, 255),
+            background: Color::new(240, 217, 181, 255),
+            low_time: Color::new(200, 30, 30, 255),
+            success: Color::new(40, 110, 40, 255),
+            error: Color::new(200, 30, 30, 255),
+            inaccuracy: Color::new(170, 130, 0, 255),
+            mistake: Color::new(210, 100, 20, 255),
+            blunder: Color::new(130, 0, 0, 255)
+        }
+    }
+
+    // Uses the Okabe-Ito palette, so the two sides stay distinguishable with any form of colour blindness
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast".to_string(),
+            light_square: Color::new(230, 230, 230, 255),
+            dark_square: Color::new(40, 40, 40, 255),
+            red_piece: Color::new(230, 159, 0, 255),
+            black_piece: Color::new(0, 114, 178, 255),
+            piece_outline: Some(Color::WHITE),
+            king: Color::WHITE,
+            king_glyph: KingGlyph::CROWN,
+            highlight: Color::new(240, 228, 66, 255),
+            label: Color::WHITE,
+            background: Color::new(230, 230, 230, 255),
+            low_time: Color::new(213, 94, 0, 255),
+            success: Color::new(0, 158, 115, 255),
+            error: Color::new(213, 94, 0, 255),
+            inaccuracy: Color::new(86, 180, 233, 255),
+            mistake: Color::new(204, 121, 167, 255),
+            blunder: Color::new(213, 94, 0, 255)
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "classic" => Some(Theme::classic()),
+            "wood" => Some(Theme::wood()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            _ => None
+        }
+    }
+
+    // Accepts either the name of a built-in theme or the path of a theme file
+    pub fn from_name_or_path(s: &str) -> Result<Theme, String> {
+        if let Some(theme) = Theme::builtin(s) {
+            return Ok(theme);
+        }
+        return Theme::load(s);
+    }
+
+    pub fn load(path: &str) -> Result<Theme, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("could not read theme {}: {}", path, e))?;
+        return Theme::parse(&text).map_err(|e| format!("invalid theme {}: {}", path, e));
+    }
+
+    pub fn parse(text: &str) -> Result<Theme, String> {
+        let file: ThemeFile = toml::from_str(text).map_err(|e| e.message().to_string())?;
+
+        let base = file.base.as_deref().unwrap_or("classic");
+        let mut theme = Theme::builtin(base).ok_or(format!("unknown base theme '{}'", base))?;
+
+        if let Some(name) = file.name {
+            theme.name = name;
+        }
+
+        let colours = [
+            (&file.light_square, &mut theme.light_square),
+            (&file.dark_square, &mut theme.dark_square),
+            (&file.red_piece, &mut theme.red_piece),
+            (&file.black_piece, &mut theme.black_piece),
+            (&file.king, &mut theme.king),
+            (&file.highlight, &mut theme.highlight),
+            (&file.label, &mut theme.label),
+            (&file.background, &mut theme.background),
+            (&file.low_time, &mut theme.low_time),
+            (&file.success, &mut theme.success),
+            (&file.error, &mut theme.error),
+            (&file.inaccuracy, &mut theme.inaccuracy),
+            (&file.mistake, &mut theme.mistake),
+            (&file.blunder, &mut theme.blunder)
+        ];
+        for (value, colour) in colours {
+            if let Some(hex) = value {
+                *colour = parse_colour(hex)?;
+            }
+        }
+
+        match file.piece_outline.as_deref() {
+            Some("none") => theme.piece_outline = None,
+            Some(hex) => theme.piece_outline = Some(parse_colour(hex)?),
+            None => ()
+        }
+
+        if let Some(glyph) = file.king_glyph {
+            theme.king_glyph = KingGlyph::parse(&glyph);
+        }
+
+        return Ok(theme);
+    }
+}
+
+// Colours are written as "#rrggbb" or "#rrggbbaa"
+pub fn parse_colour(s: &str) -> Result<Color, String> {
+    let hex = s.trim_start_matches('#');
+    if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid colour '{}'", s));
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    let alpha = if hex.len() == 8 { channel(6) } else { 255 };
+
+    return Ok(Color::new(channel(0), channel(2), channel(4), alpha));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_example_theme_sets_every_colour() {
+        let theme = Theme::parse(include_str!("../themes/midnight.toml")).unwrap();
+        assert_eq!(theme.name, "midnight");
+        assert_eq!(theme.blunder, parse_colour("#7a0010").unwrap());
+        assert_eq!(theme.piece_outline, Some(Color::new(255, 255, 255, 128)));
+        assert_eq!(theme.king_glyph, KingGlyph::CROWN);
+    }
+
+    #[test]
+    fn unknown_fields_and_bad_colours_are_rejected() {
+        assert!(Theme::parse("blunders = \"#ff0000\"").is_err());
+        assert!(Theme::parse("blunder = \"red\"").is_err());
+        assert!(Theme::parse("base = \"neon\"").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::board::{from_square, to_square, Move};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bound {
+    EXACT,
+    LOWER,
+    UPPER
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: i32,
+    pub score: f32,
+    pub bound: Bound,
+    pub best: Option<Move>
+}
+
+pub const DEFAULT_TT_ENTRIES: usize = 1 << 18;
+
+// Each slot holds the packed entry and the key xored with it. Threads read and write slots without
+// locking, so a slot torn by two writers no longer matches its key and is simply ignored.
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64
+}
+
+// Score bits, then 8 bits of depth, 2 of bound and 5 + 5 of move squares with a bit saying there is a move.
+// The bound is stored from 1 so a packed entry is never zero, which is what an empty slot holds.
+fn pack(entry: &Entry) -> u64 {
+    let bound = match entry.bound {
+        Bound::EXACT => 1,
+        Bound::LOWER => 2,
+        Bound::UPPER => 3
+    };
+    let best = match entry.best {
+        Some(m) => 1 << 10 | (to_square(m.from) as u64 - 1) << 5 | (to_square(m.to) as u64 - 1),
+        None => 0
+    };
+    return (entry.score.to_bits() as u64) << 32 | (entry.depth.clamp(0, 255) as u64) << 24 | bound << 22 | best;
+}
+
+fn unpack(key: u64, data: u64) -> Entry {
+    let bound = match data >> 22 & 3 {
+        1 => Bound::EXACT,
+        2 => Bound::LOWER,
+        _ => Bound::UPPER
+    };
+    let best = if data & 1 << 10 != 0 {
+        Some(Move::new(from_square((data >> 5 & 31) as i32 + 1), from_square((data & 31) as i32 + 1)))
+    } else {
+        None
+    };
+    return Entry { key: key, depth: (data >> 24 & 255) as i32, score: f32::from_bits((data >> 32) as u32), bound: bound, best: best };
+}
+
+pub struct TranspositionTable {
+    entries: Vec<Slot>
+}
+
+impl TranspositionTable {
+    // The size is rounded up to a power of two so the index is a simple mask
+    pub fn new(size: usize) -> Self {
+        let entries = (0..size.next_power_of_two()).map(|_| Slot { check: AtomicU64::new(0), data: AtomicU64::new(0) }).collect();
+        Self { entries: entries }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        return key as usize & (self.entries.len() - 1);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let slot = &self.entries[self.index(key)];
+        let data = slot.data.load(Ordering::Relaxed);
+        if data == 0 || slot.check.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        return Some(unpack(key, data));
+    }
+
+    // A different position always takes the slot, the same position only replaces a shallower result
+    pub fn store(&self, entry: Entry) {
+        if let Some(old) = self.probe(entry.key) {
+            if old.depth > entry.depth {
+                return;
+            }
+        }
+
+        let slot = &self.entries[self.index(entry.key)];
+        let data = pack(&entry);
+        slot.check.store(entry.key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::WIN_SCORE;
+
+    fn entries() -> Vec<Entry> {
+        let corners = Move::new(from_square(1), from_square(32));
+        let mut entries = Vec::new();
+        for (i, score) in [0.0, 1.25, -3.5, WIN_SCORE - 7.0, -(WIN_SCORE - 12.0)].into_iter().enumerate() {
+            for (j, bound) in [Bound::EXACT, Bound::LOWER, Bound::UPPER].into_iter().enumerate() {
+                for (k, best) in [None, Some(corners), Some(Move::new(from_square(32), from_square(1)))].into_iter().enumerate() {
+                    let key = 0x9e37_79b9_7f4a_7c15u64.wrapping_mul((i * 9 + j * 3 + k + 1) as u64);
+                    entries.push(Entry { key: key, depth: [0, 7, 255][k], score: score, bound: bound, best: best });
+                }
+            }
+        }
+        return entries;
+    }
+
+    fn same(a: &Entry, b: &Entry) -> bool {
+        return a.key == b.key && a.depth == b.depth && a.score == b.score && a.bound == b.bound && a.best == b.best;
+    }
+
+    #[test]
+    fn entries_survive_packing() {
+        for entry in entries() {
+            let unpacked = unpack(entry.key, pack(&entry));
+            assert!(same(&entry, &unpacked), "{:?} came back as {:?}", entry, unpacked);
+        }
+    }
+
+    #[test]
+    fn stored_entries_are_found() {
+        for entry in entries() {
+            let tt = TranspositionTable::new(16);
+            tt.store(entry);
+            let found = tt.probe(entry.key).expect("the entry was just stored");
+            assert!(same(&entry, &found), "{:?} came back as {:?}", entry, found);
+        }
+    }
+
+    #[test]
+    fn a_different_key_in_the_same_slot_misses() {
+        let tt = TranspositionTable::new(16);
+        let entry = entries()[4];
+        tt.store(entry);
+        assert!(tt.probe(entry.key ^ 16).is_none());
+        assert!(tt.probe(entry.key ^ 1 << 40).is_none());
+    }
+
+    #[test]
+    fn depth_is_clamped_to_what_fits() {
+        let entry = Entry { key: 1, depth: 300, score: 0.5, bound: Bound::EXACT, best: None };
+        assert_eq!(unpack(1, pack(&entry)).depth, 255);
+    }
+}
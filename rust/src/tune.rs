@@ -0,0 +1,202 @@
+use crate::board::{Board, RuleVariant};
+use crate::eval::{evaluate, EvalParams};
+use crate::pdn::{self, Outcome};
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+pub const TUNE_ITERATIONS: usize = 50;
+const TUNE_STEP: f32 = 0.01;
+
+// The first few plies of every game are left out, they say little about who went on to win
+const SKIP_OPENING_PLIES: usize = 8;
+
+struct Sample {
+    board: Board,
+    // The final result as red scores it
+    result: f32
+}
+
+// Texel tuning only uses quiet positions, where the static evaluation is not about to be overturned by a capture
+fn is_quiet(board: &Board) -> bool {
+    return !board.is_successive() && !board.get_all_legal_moves(board.get_turn()).iter().any(|m| m.is_kill());
+}
+
+fn push_sample(samples: &mut Vec<Sample>, board: Board, outcome: Outcome) {
+    if is_quiet(&board) && !board.is_game_over() {
+        samples.push(Sample { board: board, result: outcome.red_score() });
+    }
+}
+
+// Reads either a PDN file or a positions file as written by `checkers arena --dump`, one "<fen> <result>" per line
+fn load_samples(path: &Path, samples: &mut Vec<Sample>) -> Result<(), String> {
+    if path.extension().is_some_and(|extension| extension == "pdn") {
+        for game in pdn::load(path)? {
+            let game = match game {
+                Ok(game) => game,
+                Err(e) => {
+                    eprintln!("Skipping {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let Some(outcome) = game.outcome else { continue };
+            for board in game.game.positions().into_iter().skip(SKIP_OPENING_PLIES) {
+                push_sample(samples, board, outcome);
+            }
+        }
+        return Ok(());
+    }
+
+    let text = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (fen, result) = line.rsplit_once(' ').ok_or(format!("{}:{}: expected a FEN and a result", path.display(), number + 1))?;
+        let outcome = Outcome::parse(result).ok_or(format!("{}:{}: unknown result '{}'", path.display(), number + 1, result))?;
+        let board = Board::from_fen(fen, RuleVariant::CASUAL).map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))?;
+        push_sample(samples, board, outcome);
+    }
+    return Ok(());
+}
+
+// Every weight that is tuned. The pawn stays at 1 so the scores keep their unit, and the endgame
+// threshold is a piece count rather than a weight.
+fn weights(params: &mut EvalParams) -> Vec<&mut f32> {
+    let mut weights = vec![
+        &mut params.king,
+        &mut params.back_rank,
+        &mut params.centre,
+        &mut params.advancement,
+        &mut params.runaway,
+        &mut params.mobility,
+        &mut params.trapped_king,
+        &mut params.dog_hole,
+        &mut params.endgame_trade,
+        &mut params.endgame_distance
+    ];
+    weights.extend(params.pawn_table.iter_mut());
+    weights.extend(params.king_table.iter_mut());
+    return weights;
+}
+
+fn sigmoid(score: f32, k: f64) -> f64 {
+    return 1.0 / (1.0 + (-k * score as f64).exp());
+}
+
+// Mean squared difference between the results and the win probabilities the evaluation predicts
+fn error(samples: &[Sample], params: &EvalParams, k: f64) -> f64 {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = samples.len().div_ceil(threads).max(1);
+
+    let total: f64 = thread::scope(|scope| {
+        let handles: Vec<_> = samples.chunks(chunk).map(|chunk| scope.spawn(move || {
+            return chunk.iter().map(|sample| {
+                let difference = sample.result as f64 - sigmoid(evaluate(&sample.board, params), k);
+                return difference * difference;
+            }).sum::<f64>();
+        })).collect();
+        return handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+    });
+
+    return total / samples.len() as f64;
+}
+
+// The scaling constant that turns evaluations into probabilities is fitted first, with a golden section search
+fn fit_k(samples: &[Sample], params: &EvalParams) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.05, 10.0);
+
+    while high - low > 0.01 {
+        let a = high - ratio * (high - low);
+        let b = low + ratio * (high - low);
+        if error(samples, params, a) < error(samples, params, b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+
+    return (low + high) / 2.0;
+}
+
+// The weights are f32, written out as f64 they would show up as 0.12999999523162842
+fn round_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(f) => *f = (*f * 10000.0).round() / 10000.0,
+        toml::Value::Array(items) => items.iter_mut().for_each(round_floats),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, value)| round_floats(value)),
+        _ => ()
+    }
+}
+
+fn save(params: &EvalParams, path: &Path) -> Result<(), String> {
+    let mut value = toml::Value::try_from(params).map_err(|e| e.to_string())?;
+    round_floats(&mut value);
+    let text = toml::to_string_pretty(&value).map_err(|e| e.to_string())?;
+    return fs::write(path, text).map_err(|e| format!("could not write {}: {}", path.display(), e));
+}
+
+// `checkers tune [--weights start.toml] [--out tuned.toml] [--iterations n] <games.pdn|positions.txt>...`
+// adjusts every weight one step at a time for as long as that lowers the prediction error
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut params = EvalParams::default();
+    let mut out = "tuned.toml".to_string();
+    let mut iterations = TUNE_ITERATIONS;
+    let mut files: Vec<&str> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--weights" => params = EvalParams::load(Path::new(args.next().ok_or("--weights expects a file")?))?,
+            "--out" => out = args.next().ok_or("--out expects a file")?.clone(),
+            "--iterations" => {
+                let value = args.next().ok_or("--iterations expects a number")?;
+                iterations = value.parse().map_err(|_| format!("invalid number of iterations '{}'", value))?;
+            },
+            _ => files.push(arg)
+        }
+    }
+
+    let mut samples = Vec::new();
+    for file in &files {
+        load_samples(Path::new(file), &mut samples)?;
+    }
+    if samples.is_empty() {
+        return Err("usage: checkers tune [--weights start.toml] [--out tuned.toml] [--iterations n] <games.pdn|positions.txt>...".to_string());
+    }
+
+    let k = fit_k(&samples, &params);
+    let mut best = error(&samples, &params, k);
+    println!("{} positions, K = {:.3}, starting error {:.6}", samples.len(), k, best);
+
+    for iteration in 0..iterations {
+        let mut improved = false;
+
+        for i in 0..weights(&mut params).len() {
+            for step in [TUNE_STEP, -TUNE_STEP] {
+                let mut candidate = params.clone();
+                *weights(&mut candidate)[i] += step;
+                let e = error(&samples, &candidate, k);
+                if e < best {
+                    params = candidate;
+                    best = e;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+
+        // Written after every iteration so a long run can be stopped at any point
+        save(&params, Path::new(&out))?;
+        println!("Iteration {}: error {:.6}", iteration + 1, best);
+        if !improved {
+            break;
+        }
+    }
+
+    println!("Tuned weights written to {}", out);
+    return Ok(());
+}
@@ -0,0 +1,55 @@
+use crate::board::{Board, PieceKind, Player};
+
+// Random keys for Zobrist hashing, generated at compile time with splitmix64 so hashes are stable between runs
+const fn splitmix(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return z ^ (z >> 31);
+}
+
+const fn generate<const N: usize>(seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut i = 0;
+    while i < N {
+        keys[i] = splitmix(seed.wrapping_add((i as u64).wrapping_mul(0x9E3779B97F4A7C15)));
+        i += 1;
+    }
+    return keys;
+}
+
+// One key per piece type per square, indexed by piece_index(piece) * 64 + square
+const PIECE_KEYS: [u64; 4 * 64] = generate(1);
+const SUCCESSIVE_KEYS: [u64; 64] = generate(2);
+const RED_TO_MOVE: u64 = splitmix(3);
+
+fn piece_index(kind: PieceKind, player: Player) -> usize {
+    return match (kind, player) {
+        (PieceKind::PAWN, Player::RED) => 0,
+        (PieceKind::KING, Player::RED) => 1,
+        (PieceKind::PAWN, Player::BLACK) => 2,
+        (PieceKind::KING, Player::BLACK) => 3
+    };
+}
+
+impl Board {
+    pub fn hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for (square, piece) in self.pieces.iter().enumerate() {
+            if let Some(piece) = piece {
+                hash ^= PIECE_KEYS[piece_index(piece.kind, piece.player) * 64 + square];
+            }
+        }
+
+        if self.player_turn == Player::RED {
+            hash ^= RED_TO_MOVE;
+        }
+        if self.is_successive() {
+            let (row, col) = self.get_successive();
+            hash ^= SUCCESSIVE_KEYS[row as usize * 8 + col as usize];
+        }
+
+        return hash;
+    }
+}